@@ -0,0 +1,142 @@
+use crate::{
+    config,
+    error::{CheckError, Error},
+};
+
+use kube::{
+    client::AuthError,
+    config::{KubeConfigOptions, Kubeconfig},
+    Client, Config,
+};
+use std::{
+    path::Path,
+    time::{Duration, Instant},
+};
+
+/// How long to wait for the API server to connect and to answer
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Why the API server couldn't be asked for its version
+enum Failure {
+    Unauthorized(String),
+    Expired(String),
+    Unreachable(String),
+}
+
+impl From<kube::Error> for Failure {
+    fn from(err: kube::Error) -> Failure {
+        match err {
+            kube::Error::Api(response) if response.code == 401 => {
+                Failure::Unauthorized(response.message)
+            }
+            // The token or exec plugin couldn't give credentials, when building
+            // the client or when refreshing them for the request
+            kube::Error::Auth(err) => Failure::Expired(err.to_string()),
+            kube::Error::Service(err) if err.is::<AuthError>() => Failure::Expired(err.to_string()),
+            err => Failure::Unreachable(err.to_string()),
+        }
+    }
+}
+
+/// The version of the API server, none when the credentials were accepted but
+/// aren't allowed to read it
+async fn version(kubeconfig: Kubeconfig) -> Result<Option<String>, Failure> {
+    let mut config = Config::from_custom_kubeconfig(kubeconfig, &KubeConfigOptions::default())
+        .await
+        .map_err(|err| Failure::Unreachable(err.to_string()))?;
+    config.connect_timeout = Some(TIMEOUT);
+    config.read_timeout = Some(TIMEOUT);
+
+    let client = Client::try_from(config)?;
+    match client.apiserver_version().await {
+        Ok(info) => Ok(Some(info.git_version)),
+        Err(kube::Error::Api(response)) if response.code == 403 => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Asks the API server of `ctx` for its version with the credentials of
+/// `kubeconfig`, describing how it went and how long it took
+fn probe(ctx: &str, kubeconfig: Kubeconfig) -> Result<String, CheckError> {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .map_err(|err| CheckError::Unreachable {
+            ctx: ctx.to_owned(),
+            millis: 0,
+            reason: err.to_string(),
+        })?;
+
+    let start = Instant::now();
+    let result = runtime.block_on(version(kubeconfig));
+    let millis = start.elapsed().as_millis();
+    let ctx = ctx.to_owned();
+
+    match result {
+        Ok(Some(version)) => Ok(format!(
+            "{} is reachable in {}ms, Kubernetes {}",
+            ctx, millis, version
+        )),
+        Ok(None) => Ok(format!(
+            "{} is reachable in {}ms, not allowed to read the version",
+            ctx, millis
+        )),
+        Err(Failure::Unauthorized(reason)) => Err(CheckError::Unauthorized {
+            ctx,
+            millis,
+            reason,
+        }),
+        Err(Failure::Expired(reason)) => Err(CheckError::Expired {
+            ctx,
+            millis,
+            reason,
+        }),
+        Err(Failure::Unreachable(reason)) => Err(CheckError::Unreachable {
+            ctx,
+            millis,
+            reason,
+        }),
+    }
+}
+
+/// Checks that the API server of `ctx`, or of the current context, accepts its
+/// credentials
+pub fn check(ctx: Option<&str>) -> Result<(), Error> {
+    let session = config::get_current_session();
+    let current = session.config.current_context.clone();
+    // Another context is checked with the kubeconfig files alone, so that the
+    // session's user and impersonation don't leak into it
+    let (ctx, configs) = match ctx {
+        Some(ctx) if current.as_deref() != Some(ctx) => (ctx.to_owned(), config::get(None)),
+        Some(ctx) => (ctx.to_owned(), session),
+        None => (
+            current.ok_or(Error::Check(CheckError::NoCurrentContext))?,
+            session,
+        ),
+    };
+
+    let context = configs
+        .config
+        .contexts
+        .iter()
+        .find(|context| context.name == ctx)
+        .ok_or_else(|| Error::Check(CheckError::ContextNotFound { ctx: ctx.clone() }))?;
+    let kubeconfig = config::build(context, None, &configs);
+
+    println!("{}", probe(&ctx, kubeconfig).map_err(Error::Check)?);
+    Ok(())
+}
+
+/// Checks the credentials of the session file at `path` right after switching
+/// to `ctx`, reporting on stderr as stdout holds the KUBECONFIG for the shell
+pub fn verify(ctx: &str, path: &Path) -> Result<(), Error> {
+    let kubeconfig = Kubeconfig::read_from(path).map_err(|err| {
+        Error::Check(CheckError::Read {
+            file: path.display().to_string(),
+            reason: err.to_string(),
+        })
+    })?;
+
+    eprintln!("{}", probe(ctx, kubeconfig).map_err(Error::Check)?);
+    Ok(())
+}
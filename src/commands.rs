@@ -1,14 +1,13 @@
-use crate::config;
-use crate::error::SetContextError;
+use crate::config::{self, KubeConfigs};
+use crate::error::{Error, ListNamespacesError, SetContextError};
+use crate::picker::Picker;
 
+use k8s_openapi::api::core::v1::Namespace;
+use serde::Deserialize;
 use std::{
-    io::Cursor,
     path::Path,
     process::{Command, Stdio},
 };
-extern crate skim;
-use kube::config::Kubeconfig;
-use skim::prelude::*;
 
 pub fn set_default_namespace(ns: &str, ctx: &str, target: &Path) {
     Command::new("kubectl")
@@ -40,45 +39,61 @@ pub fn set_default_context(ctx: &str, target: &Path) {
     }
 }
 
-pub fn get_namespaces() -> Vec<String> {
-    let output = Command::new("kubectl")
-        .args(["get", "namespace", "-o=custom-columns=Name:.metadata.name"])
+#[derive(Deserialize)]
+struct NamespaceList {
+    items: Vec<Namespace>,
+}
+
+/// Lists the namespaces of the current context, only those matching the label
+/// `selector` if given
+pub fn get_namespaces(selector: Option<&str>) -> Result<Vec<Namespace>, ListNamespacesError> {
+    let mut command = Command::new("kubectl");
+    command.args(["get", "namespace", "--request-timeout=5s", "-o=json"]);
+    if let Some(selector) = selector {
+        command.arg(format!("--selector={}", selector));
+    }
+    let output = command
+        .stdin(Stdio::null())
         .output()
-        .unwrap();
+        .map_err(|err| ListNamespacesError::Unavailable(err.to_string()))?;
 
-    let string = String::from_utf8(output.stdout).unwrap();
-    string.lines().skip(1).map(ToOwned::to_owned).collect()
-}
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        return Err(if stderr.contains("(Forbidden)") {
+            ListNamespacesError::Forbidden
+        } else {
+            ListNamespacesError::Unavailable(stderr)
+        });
+    }
 
-/// Prompts the user to select an item from a list.
-/// Returns the selected item or `None` if no item was selected
-pub fn selectable_list(input: Vec<String>) -> Option<String> {
-    let input: Vec<String> = input.into_iter().rev().collect();
-    let options = SkimOptionsBuilder::default().multi(false).build().unwrap();
-    let item_reader = SkimItemReader::default();
+    serde_json::from_slice::<NamespaceList>(&output.stdout)
+        .map(|list| list.items)
+        .map_err(|err| ListNamespacesError::Unavailable(err.to_string()))
+}
 
-    let items = item_reader.of_bufread(Cursor::new(input.join("\n")));
-    Skim::run_with(&options, Some(items))
-        .and_then(|out| match out.final_key {
-            Key::Enter => Some(out.selected_items),
-            _ => None,
-        })
-        .filter(|selected_items| !selected_items.is_empty())
-        .map(|selected_items| selected_items[0].output().to_string())
+/// Prompts the user to select a `prompt` from a list with the configured picker,
+/// starting with `query` as the search
+pub fn selectable_list(
+    input: Vec<String>,
+    query: Option<&str>,
+    prompt: &'static str,
+) -> Result<String, Error> {
+    Picker::configured().select(input, query, prompt)
 }
 
-pub fn set_namespace(ctx: &str, selection: &str, temp_dir: &str, config: &Kubeconfig) -> String {
-    let choice = config.contexts.iter().find(|x| x.name == ctx);
-    config::write(choice.unwrap(), Some(selection), temp_dir, config)
+pub fn set_namespace(ctx: &str, selection: &str, temp_dir: &str, configs: &KubeConfigs) -> String {
+    let choice = configs.config.contexts.iter().find(|x| x.name == ctx);
+    config::write(choice.unwrap(), Some(selection), temp_dir, configs)
 }
 
 pub fn set_context(
     ctx: &str,
+    namespace: Option<&str>,
     temp_dir: &str,
-    config: &Kubeconfig,
+    configs: &KubeConfigs,
 ) -> Result<String, SetContextError> {
-    if let Some(choice) = config.contexts.iter().find(|x| x.name == ctx) {
-        let filename = config::write(choice, None, temp_dir, config);
+    if let Some(choice) = configs.config.contexts.iter().find(|x| x.name == ctx) {
+        let filename = config::write(choice, namespace, temp_dir, configs);
         Ok(filename)
     } else {
         Err(SetContextError::KubeContextNotFound {
@@ -0,0 +1,172 @@
+use crate::Cli;
+
+use clap::CommandFactory;
+use clap_complete::Shell;
+
+/// Subcommands whose `-v` values are completed by kubesess itself, with the
+/// completion subcommand listing them
+const VALUES: [(&str, &str); 4] = [
+    ("context", "completion-context"),
+    ("default-context", "completion-context"),
+    ("namespace", "completion-namespace"),
+    ("default-namespace", "completion-namespace"),
+];
+
+/// Print completions for the kubesess command itself, generated from the clap
+/// definition. clap only knows static values, so the generated script is
+/// extended to complete `-v` through the completion subcommands.
+pub fn print_completions(shell: Shell) {
+    let mut script = Vec::new();
+    clap_complete::generate(shell, &mut Cli::command(), "kubesess", &mut script);
+    let script = String::from_utf8(script).expect("completions are valid UTF-8");
+
+    let script = match shell {
+        Shell::Bash => format!("{}\n{}", script, bash_values()),
+        Shell::Zsh => zsh(script),
+        Shell::Fish => format!("{}\n{}", script, fish_values()),
+        Shell::PowerShell => patch(
+            &script,
+            "    $completions = @(switch ($command) {",
+            &format!(
+                "{}\n    $completions = @(switch ($command) {{",
+                powershell_values()
+            ),
+            1,
+        ),
+        Shell::Elvish => patch(
+            &script,
+            "    $completions[$command]",
+            &format!("{}\n    $completions[$command]", elvish_values()),
+            1,
+        ),
+        _ => script,
+    };
+    print!("{}", script);
+}
+
+/// Replaces the first `count` occurrences of `anchor` in the generated script.
+/// The anchors are parts of clap_complete's output, so a missing one means an
+/// upgrade changed it and the values would silently stop being completed.
+fn patch(script: &str, anchor: &str, with: &str, count: usize) -> String {
+    debug_assert!(
+        script.contains(anchor),
+        "clap_complete no longer generates {:?}",
+        anchor
+    );
+    script.replacen(anchor, with, count)
+}
+
+fn bash_values() -> String {
+    let cases: String = VALUES
+        .iter()
+        .map(|(command, completion)| format!("    {}) completion={} ;;\n", command, completion))
+        .collect();
+    format!(
+        r#"# kubesess completes the values of -v itself
+_kubesess_values() {{
+  local completion prev="${{COMP_WORDS[COMP_CWORD-1]}}"
+  case "${{COMP_WORDS[1]}}" in
+{}  esac
+  if [[ -n "$completion" && ( "$prev" == -v || "$prev" == --value ) ]]; then
+    local IFS=$'\n'
+    COMPREPLY=($(kubesess "$completion" --describe -v "${{COMP_WORDS[COMP_CWORD]}}" 2>/dev/null | cut -f1))
+    return 0
+  fi
+  _kubesess "$@"
+}}
+
+complete -F _kubesess_values -o bashdefault -o default kubesess
+"#,
+        cases
+    )
+}
+
+/// zsh completes the `-v` values through an action in every `_arguments` spec
+fn zsh(script: String) -> String {
+    let cases: String = VALUES
+        .iter()
+        .map(|(command, completion)| {
+            format!(
+                "    (*:kubesess-command-{}:*) _kubesess_describe {} ;;\n",
+                command, completion
+            )
+        })
+        .collect();
+    let values = format!(
+        r#"(( $+functions[_kubesess_values] )) ||
+_kubesess_values() {{
+  case $curcontext in
+{}    (*) return 1 ;;
+  esac
+}}
+(( $+functions[_kubesess_describe] )) ||
+_kubesess_describe() {{
+  local line
+  local -a candidates
+  for line in ${{(f)"$(kubesess "$1" --describe 2>/dev/null)"}}; do
+    candidates+=("${{${{line%%$'\t'*}}//:/\\:}}:${{line#*$'\t'}}")
+  done
+  _describe -t values 'value' candidates
+}}
+
+_kubesess "$@""#,
+        cases
+    );
+    let script = patch(&script, ":VALUE: '", ":VALUE:_kubesess_values'", usize::MAX);
+    patch(&script, r#"_kubesess "$@""#, &values, 1)
+}
+
+fn fish_values() -> String {
+    VALUES
+        .iter()
+        .map(|(command, completion)| {
+            format!(
+                "complete -c kubesess -n \"__fish_seen_subcommand_from {}; and contains -- (commandline -opc)[-1] -v --value\" -f -a '(kubesess {} --describe 2>/dev/null)'\n",
+                command, completion
+            )
+        })
+        .collect()
+}
+
+fn powershell_values() -> String {
+    let cases: String = VALUES
+        .iter()
+        .map(|(command, completion)| {
+            format!("        'kubesess;{}' {{ '{}' }}\n", command, completion)
+        })
+        .collect();
+    format!(
+        r#"    # kubesess completes the values of -v itself
+    $previous = if ($wordToComplete) {{ $commandElements[-2] }} else {{ $commandElements[-1] }}
+    $completion = switch ($command) {{
+{}    }}
+    if ($completion -and "$previous" -in '-v', '--value') {{
+        return kubesess $completion --describe -v $wordToComplete 2>$null | ForEach-Object {{
+            $name, $description = $_ -split "`t", 2
+            [CompletionResult]::new($name, $name, [CompletionResultType]::ParameterValue, $description)
+        }}
+    }}
+"#,
+        cases
+    )
+}
+
+fn elvish_values() -> String {
+    let completions: Vec<String> = VALUES
+        .iter()
+        .map(|(command, completion)| format!("&'kubesess;{}'={}", command, completion))
+        .collect();
+    format!(
+        r#"    # kubesess completes the values of -v itself
+    var values = [{}]
+    if (and (has-key $values $command) (> (count $words) 2) (has-value [-v --value] $words[-2])) {{
+        e:kubesess $values[$command] --describe -v $words[-1] | from-lines | each {{|line|
+            var name description = (str:split "\t" $line)
+            cand $name $description
+        }}
+        return
+    }}
+"#,
+        completions.join(" ")
+    )
+}
@@ -1,8 +1,11 @@
-use crate::{KUBECONFIG, KUBESESSCONFIG};
+use crate::{DEST, KUBECONFIG, KUBESESSCONFIG, SETTINGS};
+use base64::{engine::general_purpose::STANDARD, Engine};
 use kube::config::Kubeconfig;
-use kube::config::NamedContext;
+use kube::config::{NamedAuthInfo, NamedCluster, NamedContext};
+use secrecy::SecretString;
+use std::collections::BTreeMap;
 use std::fs::{self, File};
-use std::io::BufWriter;
+use std::io::{self, BufWriter};
 use std::path::Path;
 use std::path::PathBuf;
 
@@ -11,6 +14,109 @@ pub struct KubeConfigs {
     pub configs: Vec<(Kubeconfig, PathBuf)>,
 }
 
+/// A context to choose from, labelled with its file when the name is taken by a
+/// context in another file as well
+pub struct ContextOption {
+    pub label: String,
+    pub name: String,
+    pub path: PathBuf,
+    pub cluster: String,
+    pub user: String,
+}
+
+impl KubeConfigs {
+    /// The file `ctx` is defined in, the first one when several define it
+    pub fn source(&self, ctx: &str) -> Option<&Kubeconfig> {
+        self.configs
+            .iter()
+            .map(|(kubeconfig, _)| kubeconfig)
+            .find(|kubeconfig| {
+                kubeconfig
+                    .contexts
+                    .iter()
+                    .any(|context| context.name == ctx)
+            })
+    }
+
+    /// The kubeconfig files, without the session files written by kubesess
+    fn files(&self) -> impl Iterator<Item = &(Kubeconfig, PathBuf)> {
+        self.configs
+            .iter()
+            .filter(|(_, path)| !path.starts_with(DEST.as_str()))
+    }
+
+    /// Names defined in more than one file, with the files defining them
+    fn duplicates<'a, I>(
+        &'a self,
+        names: impl Fn(&'a Kubeconfig) -> I,
+    ) -> BTreeMap<String, Vec<PathBuf>>
+    where
+        I: Iterator<Item = &'a String>,
+    {
+        let mut files: BTreeMap<String, Vec<PathBuf>> = BTreeMap::new();
+        for (kubeconfig, path) in self.files() {
+            for name in names(kubeconfig) {
+                files.entry(name.clone()).or_default().push(path.clone());
+            }
+        }
+        files.retain(|_, paths| paths.len() > 1);
+        files
+    }
+
+    /// Context names defined in more than one file, with the files defining them
+    pub fn duplicate_contexts(&self) -> BTreeMap<String, Vec<PathBuf>> {
+        self.duplicates(|kubeconfig| kubeconfig.contexts.iter().map(|x| &x.name))
+    }
+
+    /// Cluster names defined in more than one file, with the files defining them
+    pub fn duplicate_clusters(&self) -> BTreeMap<String, Vec<PathBuf>> {
+        self.duplicates(|kubeconfig| kubeconfig.clusters.iter().map(|x| &x.name))
+    }
+
+    /// User names defined in more than one file, with the files defining them
+    pub fn duplicate_users(&self) -> BTreeMap<String, Vec<PathBuf>> {
+        self.duplicates(|kubeconfig| kubeconfig.auth_infos.iter().map(|x| &x.name))
+    }
+
+    /// The contexts to pick from, one per file for duplicate names
+    pub fn context_options(&self) -> Vec<ContextOption> {
+        let duplicates = self.duplicate_contexts();
+        self.files()
+            .flat_map(|(kubeconfig, path)| {
+                kubeconfig.contexts.iter().map(|context| ContextOption {
+                    label: if duplicates.contains_key(&context.name) {
+                        format!("{} ({})", context.name, path.display())
+                    } else {
+                        context.name.clone()
+                    },
+                    name: context.name.clone(),
+                    path: path.clone(),
+                    cluster: context
+                        .context
+                        .as_ref()
+                        .map(|c| c.cluster.clone())
+                        .unwrap_or_default(),
+                    user: context
+                        .context
+                        .as_ref()
+                        .map(|c| c.user.clone())
+                        .unwrap_or_default(),
+                })
+            })
+            .collect()
+    }
+
+    /// Moves `path` to the front, so that its contexts, clusters and users win
+    /// over those with the same names in the other files
+    pub fn prefer(mut self, path: &Path) -> KubeConfigs {
+        if let Some(index) = self.configs.iter().position(|(_, p)| p == path) {
+            let preferred = self.configs.remove(index);
+            self.configs.insert(0, preferred);
+        }
+        merge(self.configs)
+    }
+}
+
 pub fn get(current_session: Option<&str>) -> KubeConfigs {
     use std::collections::HashSet;
     use std::path::PathBuf;
@@ -27,23 +133,35 @@ pub fn get(current_session: Option<&str>) -> KubeConfigs {
         .map(PathBuf::from)
         .collect();
 
-    let mut conifg = Kubeconfig::default();
     let mut configs = Vec::new();
 
     for path in config_paths {
-        match Kubeconfig::read_from(&path) {
-            Ok(kubeconfig) => {
-                configs.push((kubeconfig.clone(), path.clone()));
+        // Reading from an absolute path makes the file references absolute too,
+        // so they keep working from the session files in the cache
+        let path = std::path::absolute(&path).unwrap_or(path);
+        if let Ok(mut kubeconfig) = Kubeconfig::read_from(&path) {
+            if let Some(dir) = path.parent() {
+                absolutize_exec_commands(&mut kubeconfig, dir);
+            }
+            configs.push((kubeconfig, path));
+        }
+    }
 
-                conifg.contexts.extend(kubeconfig.contexts.into_iter());
-                conifg.clusters.extend(kubeconfig.clusters.into_iter());
-                conifg.auth_infos.extend(kubeconfig.auth_infos.into_iter());
+    merge(configs)
+}
 
-                if conifg.current_context.is_none() {
-                    conifg.current_context = kubeconfig.current_context.clone();
-                }
-            }
-            Err(_) => {}
+fn merge(configs: Vec<(Kubeconfig, PathBuf)>) -> KubeConfigs {
+    let mut conifg = Kubeconfig::default();
+
+    for (kubeconfig, _) in &configs {
+        conifg.contexts.extend(kubeconfig.contexts.iter().cloned());
+        conifg.clusters.extend(kubeconfig.clusters.iter().cloned());
+        conifg
+            .auth_infos
+            .extend(kubeconfig.auth_infos.iter().cloned());
+
+        if conifg.current_context.is_none() {
+            conifg.current_context = kubeconfig.current_context.clone();
         }
     }
 
@@ -53,12 +171,113 @@ pub fn get(current_session: Option<&str>) -> KubeConfigs {
     }
 }
 
+/// Makes relative exec plugin commands such as `./bin/token` absolute against
+/// `dir`, which is where kubectl resolves them from. Bare command names are
+/// looked up in `PATH` and are left alone.
+fn absolutize_exec_commands(kubeconfig: &mut Kubeconfig, dir: &Path) {
+    let commands = kubeconfig
+        .auth_infos
+        .iter_mut()
+        .filter_map(|auth_info| auth_info.auth_info.as_mut())
+        .filter_map(|auth_info| auth_info.exec.as_mut())
+        .filter_map(|exec| exec.command.as_mut());
+
+    for command in commands {
+        let path = Path::new(command.as_str());
+        if path.is_relative() && path.components().count() > 1 {
+            *command = dir
+                .join(path.strip_prefix(".").unwrap_or(path))
+                .display()
+                .to_string();
+        }
+    }
+}
+
+/// The cluster named `name` from the file defining `ctx`, or from any file
+fn find_cluster<'a>(configs: &'a KubeConfigs, ctx: &str, name: &str) -> Option<&'a NamedCluster> {
+    configs
+        .source(ctx)
+        .into_iter()
+        .chain([&configs.config])
+        .find_map(|kubeconfig| kubeconfig.clusters.iter().find(|x| x.name == name))
+}
+
+/// The user named `name` from the file defining `ctx`, or from any file
+fn find_auth_info<'a>(
+    configs: &'a KubeConfigs,
+    ctx: &str,
+    name: &str,
+) -> Option<&'a NamedAuthInfo> {
+    configs
+        .source(ctx)
+        .into_iter()
+        .chain([&configs.config])
+        .find_map(|kubeconfig| kubeconfig.auth_infos.iter().find(|x| x.name == name))
+}
+
+/// Whether two kubeconfig entries are equal, which the kube types can't tell
+fn same<T: serde::Serialize>(a: &T, b: &T) -> bool {
+    serde_json::to_value(a).ok() == serde_json::to_value(b).ok()
+}
+
+/// Why the session file `session` no longer matches the kubeconfig files it was
+/// built from, if one of them changed since it was written
+pub fn staleness(session: &Kubeconfig, configs: &KubeConfigs) -> Option<String> {
+    let context = session.contexts.first()?;
+    let Some(source) = configs
+        .files()
+        .flat_map(|(kubeconfig, _)| &kubeconfig.contexts)
+        .find(|x| x.name == context.name)
+    else {
+        return Some(format!("context {} no longer exists", context.name));
+    };
+    let (Some(session_context), Some(source_context)) = (&context.context, &source.context) else {
+        return None;
+    };
+    if session_context.cluster != source_context.cluster {
+        return Some(format!(
+            "context {} uses cluster {} now",
+            context.name, source_context.cluster
+        ));
+    }
+
+    if let Some(cluster) = session.clusters.first() {
+        match find_cluster(configs, &context.name, &cluster.name) {
+            None => return Some(format!("cluster {} no longer exists", cluster.name)),
+            Some(source) if !same(cluster, source) => {
+                return Some(format!("cluster {} changed", cluster.name))
+            }
+            Some(_) => {}
+        }
+    }
+    if let Some(user) = session.auth_infos.first() {
+        // Impersonation is set on the session by `kubesess user`, which
+        // also turns a user without settings into an empty one
+        let mut auth_info = user.auth_info.clone().unwrap_or_default();
+        auth_info.impersonate = None;
+        auth_info.impersonate_groups = None;
+        match find_auth_info(configs, &context.name, &user.name) {
+            None => return Some(format!("user {} no longer exists", user.name)),
+            Some(source) if !same(&auth_info, &source.auth_info.clone().unwrap_or_default()) => {
+                return Some(format!("user {} changed", user.name))
+            }
+            Some(_) => {}
+        }
+    }
+    None
+}
+
+/// Builds a kubeconfig holding only `selected_context` with its cluster and
+/// user. The cluster, user, preferences and extensions are taken from the file
+/// the context is defined in, the cluster and user falling back to the other
+/// files like kubectl does.
 pub fn build(
     selected_context: &NamedContext,
     namespace: Option<&str>,
-    kubeconfig: &Kubeconfig,
+    configs: &KubeConfigs,
 ) -> Kubeconfig {
     let context_name = &selected_context.name;
+    let source = configs.source(context_name);
 
     // Handle the case where the context is None
     let context = match &selected_context.context {
@@ -70,19 +289,11 @@ pub fn build(
     };
 
     // Find the corresponding cluster based on the context's cluster reference
-    let cluster_name = &context.cluster;
-    let selected_cluster = kubeconfig
-        .clusters
-        .iter()
-        .find(|cluster| &cluster.name == cluster_name)
+    let selected_cluster = find_cluster(configs, context_name, &context.cluster)
         .expect("Cluster for the selected context not found");
 
     // Find the corresponding auth_info (user) based on the context's user reference
-    let user_name = &context.user;
-    let selected_auth_info = kubeconfig
-        .auth_infos
-        .iter()
-        .find(|auth_info| &auth_info.name == user_name)
+    let selected_auth_info = find_auth_info(configs, context_name, &context.user)
         .expect("Auth info for the selected context not found");
 
     // Determine the namespace: use the provided one or fallback to the context's namespace
@@ -104,10 +315,52 @@ pub fn build(
         contexts: vec![minimal_context],
         clusters: vec![selected_cluster.clone()],
         auth_infos: vec![selected_auth_info.clone()],
+        preferences: source.and_then(|source| source.preferences.clone()),
+        extensions: source.and_then(|source| source.extensions.clone()),
         ..Kubeconfig::default()
     }
 }
 
+/// Replaces the certificate and key files referenced by `kubeconfig` with their
+/// base64 encoded content, and token files with the token they hold. Returns
+/// the file that couldn't be read on error.
+pub fn inline_files(kubeconfig: &mut Kubeconfig) -> Result<(), (String, io::Error)> {
+    let encode = |path: String| {
+        fs::read(&path)
+            .map(|content| STANDARD.encode(content))
+            .map_err(|err| (path, err))
+    };
+
+    for cluster in kubeconfig
+        .clusters
+        .iter_mut()
+        .filter_map(|cluster| cluster.cluster.as_mut())
+    {
+        if let Some(path) = cluster.certificate_authority.take() {
+            cluster.certificate_authority_data = Some(encode(path)?);
+        }
+    }
+
+    for auth_info in kubeconfig
+        .auth_infos
+        .iter_mut()
+        .filter_map(|auth_info| auth_info.auth_info.as_mut())
+    {
+        if let Some(path) = auth_info.client_certificate.take() {
+            auth_info.client_certificate_data = Some(encode(path)?);
+        }
+        if let Some(path) = auth_info.client_key.take() {
+            auth_info.client_key_data = Some(SecretString::from(encode(path)?));
+        }
+        if let Some(path) = auth_info.token_file.take() {
+            let token = fs::read_to_string(&path).map_err(|err| (path, err))?;
+            auth_info.token = Some(SecretString::from(token.trim().to_string()));
+        }
+    }
+
+    Ok(())
+}
+
 fn get_file(path: &String) -> File {
     let f = std::fs::OpenOptions::new()
         .read(true)
@@ -136,9 +389,14 @@ pub fn write(
     ctx: &NamedContext,
     namespace: Option<&str>,
     dest: &str,
-    config: &Kubeconfig,
+    configs: &KubeConfigs,
 ) -> String {
-    let minimal_config = build(ctx, namespace, config);
+    let minimal_config = build(ctx, namespace, configs);
+    write_session(&minimal_config, dest, configs)
+}
+
+/// Writes a session file built by `build`, returning its name
+pub fn write_session(minimal_config: &Kubeconfig, dest: &str, configs: &KubeConfigs) -> String {
     let selected_context = minimal_config.current_context.clone().unwrap_or_default();
     let selected_ns = minimal_config
         .contexts
@@ -146,24 +404,174 @@ pub fn write(
         .and_then(|ctx| ctx.context.as_ref().and_then(|c| c.namespace.clone()))
         .unwrap_or_else(|| "default".to_string());
 
-    let filename = selected_context.to_owned() + "_" + &selected_ns.to_owned();
+    let filename = selected_context.to_owned()
+        + "_"
+        + &selected_ns.to_owned()
+        + &user_suffix(minimal_config, configs);
     let path = get_path(&filename, dest);
 
     let options = get_file(&path);
     let writer = BufWriter::new(&options);
 
-    serde_yaml::to_writer(writer, &minimal_config).unwrap();
+    serde_yaml::to_writer(writer, minimal_config).unwrap();
     filename
 }
 
-pub fn get_current_session() -> Kubeconfig {
+/// Sessions with another user than their context, or impersonating someone,
+/// get their own file so that they don't leak into shells sharing the context.
+fn user_suffix(minimal_config: &Kubeconfig, configs: &KubeConfigs) -> String {
+    let Some(context) = minimal_config.contexts.first() else {
+        return String::new();
+    };
+    let user = context
+        .context
+        .as_ref()
+        .map(|c| c.user.as_str())
+        .unwrap_or_default();
+    let original_user = configs
+        .files()
+        .flat_map(|(kubeconfig, _)| &kubeconfig.contexts)
+        .find(|x| x.name == context.name)
+        .and_then(|x| x.context.as_ref())
+        .map(|c| c.user.as_str());
+    let auth_info = minimal_config
+        .auth_infos
+        .first()
+        .and_then(|x| x.auth_info.as_ref());
+    let impersonate = auth_info.and_then(|x| x.impersonate.as_deref());
+    let groups = auth_info
+        .and_then(|x| x.impersonate_groups.clone())
+        .unwrap_or_default();
+
+    let mut suffix = String::new();
+    if original_user.is_some_and(|x| x != user) || impersonate.is_some() || !groups.is_empty() {
+        suffix = format!("@{}", user);
+    }
+    if let Some(impersonate) = impersonate {
+        suffix = format!("{}-as-{}", suffix, impersonate);
+    }
+    if !groups.is_empty() {
+        suffix = format!("{}+{}", suffix, groups.join(","));
+    }
+    suffix.replace('/', "_")
+}
+
+/// Writes `kubeconfig` to `path`, creating the parent directories if needed
+pub fn save(kubeconfig: &Kubeconfig, path: &Path) {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).expect("Could not create destination dir");
+    }
+
+    let file = get_file(&path.display().to_string());
+    let writer = BufWriter::new(&file);
+
+    serde_yaml::to_writer(writer, kubeconfig).unwrap();
+}
+
+/// All session files in the cache, which can sit in nested directories
+pub fn session_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir).into_iter().flatten().flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            files.extend(session_files(&path));
+        } else {
+            files.push(path);
+        }
+    }
+    files
+}
+
+/// What refreshing a stale session file did, with why it was stale
+pub enum Refresh {
+    Rebuilt(String),
+    /// The context or its cluster or user is gone, so switching again is needed
+    Failed(String),
+}
+
+/// Rebuilds the session file at `path` when the kubeconfig files it was built
+/// from changed, keeping its namespace, user and impersonation. Returns nothing
+/// when it is up to date.
+pub fn refresh(path: &Path, configs: &KubeConfigs) -> Option<Refresh> {
+    let session = Kubeconfig::read_from(path).ok()?;
+    let reason = staleness(&session, configs)?;
+    match rebuild(&session, configs) {
+        Some(refreshed) => {
+            save(&refreshed, path);
+            Some(Refresh::Rebuilt(reason))
+        }
+        None => Some(Refresh::Failed(reason)),
+    }
+}
+
+fn rebuild(session: &Kubeconfig, configs: &KubeConfigs) -> Option<Kubeconfig> {
+    let session_context = session.contexts.first()?.context.as_ref()?;
+
+    let mut context = configs
+        .files()
+        .flat_map(|(kubeconfig, _)| &kubeconfig.contexts)
+        .find(|x| Some(&x.name) == session.current_context.as_ref())?
+        .clone();
+    // The user may have been switched with `kubesess user`
+    let context_data = context.context.as_mut()?;
+    context_data.user = session_context.user.clone();
+    find_cluster(configs, &context.name, &context_data.cluster)?;
+    find_auth_info(configs, &context.name, &context_data.user)?;
+
+    let mut refreshed = build(&context, session_context.namespace.as_deref(), configs);
+    let impersonation = session
+        .auth_infos
+        .first()
+        .and_then(|x| x.auth_info.as_ref());
+    if let (Some(user), Some(impersonation)) = (refreshed.auth_infos.first_mut(), impersonation) {
+        let auth_info = user.auth_info.get_or_insert_with(Default::default);
+        auth_info.impersonate = impersonation.impersonate.clone();
+        auth_info.impersonate_groups = impersonation.impersonate_groups.clone();
+    }
+    Some(refreshed)
+}
+
+/// Whether a kubeconfig file was written after the session file at `path`, or
+/// too close to it for the timestamps to tell
+fn modified_since(path: &Path) -> bool {
+    let modified = |path: &Path| fs::metadata(path).and_then(|x| x.modified()).ok();
+    let Some(session) = modified(path) else {
+        return false;
+    };
+    KUBECONFIG
+        .split(':')
+        .filter(|p| !p.is_empty())
+        .any(|p| modified(Path::new(p)).is_none_or(|source| source >= session))
+}
+
+pub fn get_current_session() -> KubeConfigs {
     let current = if KUBESESSCONFIG.is_empty() {
         KUBECONFIG.split(':').next().unwrap()
     } else {
         KUBESESSCONFIG.as_str()
     };
 
-    let configs = get(Some(current));
+    get(Some(current))
+}
+
+/// The current session, its file rebuilt first when the kubeconfig it was built
+/// from changed. Used by the commands that switch within the session, since
+/// shells keep pointing at the session file after a cloud CLI rotated a token
+/// or endpoint in the file it was built from.
+pub fn get_refreshed_session() -> KubeConfigs {
+    let path = Path::new(KUBESESSCONFIG.as_str());
+    if SETTINGS.refresh_sessions && !KUBESESSCONFIG.is_empty() && modified_since(path) {
+        match refresh(path, &get(None)) {
+            Some(Refresh::Rebuilt(reason)) => {
+                eprintln!("kubesess: refreshed the session file, {}", reason)
+            }
+            Some(Refresh::Failed(reason)) => eprintln!(
+                "warning: session file is stale, {}, switch again with kc",
+                reason
+            ),
+            None => {}
+        }
+    }
 
-    configs.config
+    get_current_session()
 }
@@ -0,0 +1,329 @@
+use crate::{config, error::EditContextError, state::State, DEST};
+
+use kube::config::Kubeconfig;
+use serde_yaml::{Mapping, Value};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// The kubeconfig file defining `ctx`, found the same way `default-context` does
+fn owning_file(ctx: &str) -> Result<PathBuf, EditContextError> {
+    config::get(None)
+        .configs
+        .iter()
+        .find(|(kubeconfig, _)| {
+            kubeconfig
+                .contexts
+                .iter()
+                .any(|context| context.name == ctx)
+        })
+        .map(|(_, path)| path.clone())
+        .ok_or_else(|| EditContextError::NotFound {
+            ctx: ctx.to_owned(),
+        })
+}
+
+// The files are edited as plain YAML rather than through `Kubeconfig`, so that
+// fields kubesess doesn't know about and relative paths are kept as they are.
+
+fn read(path: &Path) -> Result<Value, EditContextError> {
+    let content = fs::read_to_string(path).map_err(|err| io_error(path, err))?;
+    serde_yaml::from_str(&content).map_err(|err| EditContextError::Io {
+        file: path.display().to_string(),
+        reason: err.to_string(),
+    })
+}
+
+fn write(path: &Path, document: &Value) -> Result<(), EditContextError> {
+    let content = serde_yaml::to_string(document).unwrap();
+    fs::write(path, content).map_err(|err| io_error(path, err))
+}
+
+fn io_error(path: &Path, err: std::io::Error) -> EditContextError {
+    EditContextError::Io {
+        file: path.display().to_string(),
+        reason: err.to_string(),
+    }
+}
+
+/// A kubeconfig with nothing in it yet
+fn empty_document() -> Value {
+    let mut document = Mapping::new();
+    document.insert("apiVersion".into(), "v1".into());
+    document.insert("kind".into(), "Config".into());
+    Value::Mapping(document)
+}
+
+/// The `key` list of `document`, created when it is missing
+fn entries<'a>(
+    document: &'a mut Value,
+    key: &str,
+    path: &Path,
+) -> Result<&'a mut Vec<Value>, EditContextError> {
+    let mapping = document
+        .as_mapping_mut()
+        .ok_or_else(|| EditContextError::NotAMapping {
+            file: path.display().to_string(),
+        })?;
+    if !matches!(mapping.get(key), Some(Value::Sequence(_))) {
+        mapping.insert(Value::from(key), Value::Sequence(Vec::new()));
+    }
+    Ok(mapping
+        .get_mut(key)
+        .and_then(Value::as_sequence_mut)
+        .expect("the list was just inserted"))
+}
+
+fn name_of(entry: &Value) -> Option<&str> {
+    entry.get("name").and_then(Value::as_str)
+}
+
+fn find<'a>(document: &'a Value, key: &str, name: &str) -> Option<&'a Value> {
+    document
+        .get(key)?
+        .as_sequence()?
+        .iter()
+        .find(|entry| name_of(entry) == Some(name))
+}
+
+/// The cluster and user names a context entry refers to
+fn references(context: &Value) -> (Option<String>, Option<String>) {
+    let field = |key: &str| {
+        context
+            .get("context")
+            .and_then(|c| c.get(key))
+            .and_then(Value::as_str)
+            .map(ToOwned::to_owned)
+    };
+    (field("cluster"), field("user"))
+}
+
+fn sessions_for(ctx: &str) -> Vec<(PathBuf, Kubeconfig)> {
+    config::session_files(Path::new(DEST.as_str()))
+        .into_iter()
+        .filter_map(|path| Some((path.clone(), Kubeconfig::read_from(&path).ok()?)))
+        .filter(|(_, session)| session.current_context.as_deref() == Some(ctx))
+        .collect()
+}
+
+/// Renames `old` to `new` in the file defining it, in the session files using
+/// it and in the state remembered for it
+pub fn rename(old: &str, new: &str) -> Result<(), EditContextError> {
+    if config::get(None)
+        .config
+        .contexts
+        .iter()
+        .any(|context| context.name == new)
+    {
+        return Err(EditContextError::AlreadyExists {
+            ctx: new.to_owned(),
+        });
+    }
+
+    let path = owning_file(old)?;
+    let mut document = read(&path)?;
+    for context in entries(&mut document, "contexts", &path)? {
+        if name_of(context) == Some(old) {
+            context["name"] = Value::from(new);
+        }
+    }
+    if document.get("current-context").and_then(Value::as_str) == Some(old) {
+        document["current-context"] = Value::from(new);
+    }
+    write(&path, &document)?;
+
+    // Shells keep pointing at the session files, so they are updated in place
+    for (session_path, mut session) in sessions_for(old) {
+        session.current_context = Some(new.to_owned());
+        for context in session.contexts.iter_mut() {
+            if context.name == old {
+                context.name = new.to_owned();
+            }
+        }
+        config::save(&session, &session_path);
+    }
+
+    let mut state = State::load();
+    if let Some(ns) = state.namespaces.remove(old) {
+        state.namespaces.insert(new.to_owned(), ns);
+    }
+    if let Some(cache) = state.namespace_cache.remove(old) {
+        state.namespace_cache.insert(new.to_owned(), cache);
+    }
+    if let Some(history) = state.namespace_history.remove(old) {
+        state.namespace_history.insert(new.to_owned(), history);
+    }
+    state.save();
+
+    println!("Renamed context {} to {} in {}", old, new, path.display());
+    Ok(())
+}
+
+/// Deletes `ctx` from the file defining it, along with its session files and,
+/// with `prune`, its cluster and user when no context in any file uses them
+pub fn delete(ctx: &str, prune: bool) -> Result<(), EditContextError> {
+    let path = owning_file(ctx)?;
+    let mut document = read(&path)?;
+    // The file may not hold the context as parsed, e.g. when it has several
+    // documents
+    let (cluster, user) = find(&document, "contexts", ctx)
+        .map(references)
+        .ok_or_else(|| EditContextError::NotFound {
+            ctx: ctx.to_owned(),
+        })?;
+
+    entries(&mut document, "contexts", &path)?.retain(|context| name_of(context) != Some(ctx));
+    if let Some(mapping) = document.as_mapping_mut() {
+        if mapping.get("current-context").and_then(Value::as_str) == Some(ctx) {
+            mapping.remove("current-context");
+        }
+    }
+
+    let mut pruned = Vec::new();
+    if prune {
+        // The contexts left in this file and those of every other file
+        let mut used: Vec<(Option<String>, Option<String>)> = document
+            .get("contexts")
+            .and_then(Value::as_sequence)
+            .map(|contexts| contexts.iter().map(references).collect())
+            .unwrap_or_default();
+        used.extend(
+            config::get(None)
+                .configs
+                .iter()
+                .filter(|(_, other)| *other != path)
+                .flat_map(|(kubeconfig, _)| &kubeconfig.contexts)
+                .filter_map(|context| context.context.as_ref())
+                .map(|context| (Some(context.cluster.clone()), Some(context.user.clone()))),
+        );
+        let (clusters, users): (Vec<_>, Vec<_>) = used.into_iter().unzip();
+
+        for (key, kind, name, used) in [
+            ("clusters", "cluster", cluster, clusters),
+            ("users", "user", user, users),
+        ] {
+            let Some(name) = name.filter(|name| !used.contains(&Some(name.clone()))) else {
+                continue;
+            };
+            let entries = entries(&mut document, key, &path)?;
+            let count = entries.len();
+            entries.retain(|entry| name_of(entry) != Some(&name));
+            if entries.len() < count {
+                pruned.push(format!("{} {}", kind, name));
+            }
+        }
+    }
+    write(&path, &document)?;
+
+    for (session_path, _) in sessions_for(ctx) {
+        fs::remove_file(&session_path).map_err(|err| io_error(&session_path, err))?;
+    }
+
+    let mut state = State::load();
+    state.namespaces.remove(ctx);
+    state.namespace_cache.remove(ctx);
+    state.namespace_history.remove(ctx);
+    state.save();
+
+    println!("Deleted context {} from {}", ctx, path.display());
+    for entry in pruned {
+        println!("Deleted unused {}", entry);
+    }
+    Ok(())
+}
+
+/// Makes the file paths in a cluster or user entry absolute, so that they still
+/// point at the same files from another directory
+fn absolutize(entry: &mut Value, section: &str, keys: &[&str], dir: &Path) {
+    if let Some(fields) = entry.get_mut(section).and_then(Value::as_mapping_mut) {
+        for key in keys {
+            if let Some(Value::String(path)) = fields.get_mut(*key) {
+                if Path::new(path.as_str()).is_relative() {
+                    *path = dir.join(&*path).display().to_string();
+                }
+            }
+        }
+    }
+}
+
+/// Copies `ctx` with its cluster and user to `target`, optionally as `name`
+pub fn copy(ctx: &str, target: &Path, name: Option<&str>) -> Result<(), EditContextError> {
+    let new_name = name.unwrap_or(ctx);
+    let source = owning_file(ctx)?;
+    let source_document = read(&source)?;
+    let source_dir = source.parent().unwrap_or(Path::new("."));
+
+    // An empty file is started like a missing one
+    let mut target_document = if target.exists() {
+        read(target)?
+    } else {
+        Value::Null
+    };
+    if target_document.is_null() {
+        target_document = empty_document();
+    }
+
+    if find(&target_document, "contexts", new_name).is_some() {
+        return Err(EditContextError::AlreadyExists {
+            ctx: new_name.to_owned(),
+        });
+    }
+
+    let mut context = find(&source_document, "contexts", ctx)
+        .ok_or_else(|| EditContextError::NotFound {
+            ctx: ctx.to_owned(),
+        })?
+        .clone();
+    let (cluster, user) = references(&context);
+
+    for (key, section, name, paths) in [
+        (
+            "clusters",
+            "cluster",
+            cluster,
+            &["certificate-authority"][..],
+        ),
+        (
+            "users",
+            "user",
+            user,
+            &["client-certificate", "client-key", "tokenFile"][..],
+        ),
+    ] {
+        let Some(name) = name else { continue };
+        let Some(entry) = find(&source_document, key, &name) else {
+            continue;
+        };
+        let mut entry = entry.clone();
+        absolutize(&mut entry, section, paths, source_dir);
+
+        match find(&target_document, key, &name) {
+            Some(existing) if *existing == entry => {}
+            Some(_) => {
+                return Err(EditContextError::Conflict {
+                    kind: section,
+                    name,
+                    file: target.display().to_string(),
+                })
+            }
+            None => entries(&mut target_document, key, target)?.push(entry),
+        }
+    }
+
+    context["name"] = Value::from(new_name);
+    entries(&mut target_document, "contexts", target)?.push(context);
+
+    if let Some(parent) = target.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent).map_err(|err| io_error(parent, err))?;
+    }
+    write(target, &target_document)?;
+
+    println!(
+        "Copied context {} to {} as {}",
+        ctx,
+        target.display(),
+        new_name
+    );
+    Ok(())
+}
@@ -4,12 +4,149 @@ use thiserror::Error;
 pub enum Error {
     #[error("failed to set context: {0}")]
     SetContext(#[source] SetContextError),
+    #[error("failed to set namespace: {0}")]
+    SetNamespace(#[source] SetNamespaceError),
+    #[error("failed to set user: {0}")]
+    SetUser(#[source] SetUserError),
     #[error("no item selected when prompted to select {prompt}")]
     NoItemSelected { prompt: &'static str },
+    #[error(
+        "cannot prompt to select {prompt} because stdin is not a terminal, pass it with -v instead"
+    )]
+    NotATerminal { prompt: &'static str },
+    #[error("failed to run picker {picker}: {reason}")]
+    Picker { picker: String, reason: String },
+    #[error("failed to import: {0}")]
+    Import(#[source] ImportError),
+    #[error("failed to edit context: {0}")]
+    EditContext(#[source] EditContextError),
+    #[error("failed to export: {0}")]
+    Export(#[source] ExportError),
+    #[error("failed to pin directory: {0}")]
+    Pin(#[source] PinError),
+    #[error("credential check failed: {0}")]
+    Check(#[source] CheckError),
 }
 
 #[derive(Error, Debug)]
 pub enum SetContextError {
     #[error("no context exists with the name {ctx}")]
     KubeContextNotFound { ctx: String },
+    #[error("no context uses a cluster with the name {cluster}")]
+    ClusterNotFound { cluster: String },
+}
+
+#[derive(Error, Debug)]
+pub enum SetNamespaceError {
+    #[error("no namespace exists with the name {ns}{}", did_you_mean(.suggestions))]
+    NamespaceNotFound {
+        ns: String,
+        suggestions: Vec<String>,
+    },
+    #[error("not allowed to list namespaces in {ctx}, use --force to switch to {ns} anyway")]
+    Forbidden { ctx: String, ns: String },
+    #[error("no namespace matches the selector {selector}")]
+    NoneSelected { selector: String },
+}
+
+#[derive(Error, Debug)]
+pub enum SetUserError {
+    #[error("no context is set in this session, switch to one first")]
+    NoCurrentContext,
+    #[error("no user exists with the name {user}")]
+    UserNotFound { user: String },
+}
+
+#[derive(Error, Debug)]
+pub enum ListNamespacesError {
+    #[error("listing namespaces is forbidden")]
+    Forbidden,
+    #[error("could not list namespaces: {0}")]
+    Unavailable(String),
+}
+
+#[derive(Error, Debug)]
+pub enum ImportError {
+    #[error("could not read {file}: {reason}")]
+    Read { file: String, reason: String },
+    #[error("{file} has no contexts, clusters or users")]
+    NothingToImport { file: String },
+    #[error("no terminal to confirm on, pass --yes to write anyway")]
+    NotConfirmed,
+}
+
+#[derive(Error, Debug)]
+pub enum EditContextError {
+    #[error("no context exists with the name {ctx}")]
+    NotFound { ctx: String },
+    #[error("a context named {ctx} already exists")]
+    AlreadyExists { ctx: String },
+    #[error("{file} already has a different {kind} named {name}")]
+    Conflict {
+        kind: &'static str,
+        name: String,
+        file: String,
+    },
+    #[error("could not update {file}: {reason}")]
+    Io { file: String, reason: String },
+    #[error("{file} is not a kubeconfig, its top level isn't a mapping")]
+    NotAMapping { file: String },
+}
+
+#[derive(Error, Debug)]
+pub enum ExportError {
+    #[error("no context exists with the name {ctx}")]
+    ContextNotFound { ctx: String },
+    #[error("could not inline {file}: {reason}")]
+    Inline { file: String, reason: String },
+    #[error("could not write {file}: {reason}")]
+    Write { file: String, reason: String },
+}
+
+#[derive(Error, Debug)]
+pub enum PinError {
+    #[error("no .kubesess file in {dir} or above it")]
+    NoPinFile { dir: String },
+    #[error("{file} is not a valid .kubesess file: {reason}")]
+    Invalid { file: String, reason: String },
+    #[error("could not access {file}: {reason}")]
+    Io { file: String, reason: String },
+}
+
+#[derive(Error, Debug)]
+pub enum CheckError {
+    #[error("no context is set in this session, switch to one first")]
+    NoCurrentContext,
+    #[error("no context exists with the name {ctx}")]
+    ContextNotFound { ctx: String },
+    #[error("could not read {file}: {reason}")]
+    Read { file: String, reason: String },
+    #[error("{ctx} is unauthorized after {millis}ms: {reason}")]
+    Unauthorized {
+        ctx: String,
+        millis: u128,
+        reason: String,
+    },
+    #[error(
+        "the credentials of {ctx} are expired or could not be obtained after {millis}ms: {reason}"
+    )]
+    Expired {
+        ctx: String,
+        millis: u128,
+        reason: String,
+    },
+    #[error("{ctx} is unreachable after {millis}ms: {reason}")]
+    Unreachable {
+        ctx: String,
+        millis: u128,
+        reason: String,
+    },
+}
+
+fn did_you_mean(suggestions: &[String]) -> String {
+    if suggestions.is_empty() {
+        String::new()
+    } else {
+        format!(", did you mean: {}?", suggestions.join(", "))
+    }
 }
@@ -0,0 +1,70 @@
+use crate::{config, error::ExportError};
+
+use std::{
+    fs::OpenOptions,
+    io::{self, Write},
+    path::Path,
+};
+#[cfg(unix)]
+use std::{
+    fs::Permissions,
+    os::unix::fs::{OpenOptionsExt, PermissionsExt},
+};
+
+/// Prints or writes a kubeconfig holding only `ctx`, its cluster and its user.
+///
+/// With `flatten` the certificates and keys are inlined, so that the file works
+/// on machines that don't have them.
+pub fn export(
+    ctx: &str,
+    namespace: Option<&str>,
+    flatten: bool,
+    output: Option<&Path>,
+) -> Result<(), ExportError> {
+    let configs = config::get(None);
+    let context = configs
+        .config
+        .contexts
+        .iter()
+        .find(|context| context.name == ctx)
+        .ok_or_else(|| ExportError::ContextNotFound {
+            ctx: ctx.to_owned(),
+        })?;
+
+    let mut kubeconfig = config::build(context, namespace, &configs);
+    kubeconfig.api_version = Some("v1".to_string());
+    kubeconfig.kind = Some("Config".to_string());
+
+    if flatten {
+        config::inline_files(&mut kubeconfig).map_err(|(file, err)| ExportError::Inline {
+            file,
+            reason: err.to_string(),
+        })?;
+    }
+
+    let content = serde_yaml::to_string(&kubeconfig).unwrap();
+    match output {
+        Some(path) => write(path, &content).map_err(|err| ExportError::Write {
+            file: path.display().to_string(),
+            reason: err.to_string(),
+        }),
+        None => {
+            print!("{}", content);
+            Ok(())
+        }
+    }
+}
+
+/// Writes the kubeconfig readable by the owner only, since it may hold keys and
+/// tokens
+fn write(path: &Path, content: &str) -> io::Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    options.mode(0o600);
+    let mut file = options.open(path)?;
+    // The mode only applies to new files
+    #[cfg(unix)]
+    file.set_permissions(Permissions::from_mode(0o600))?;
+    file.write_all(content.as_bytes())
+}
@@ -0,0 +1,288 @@
+use crate::{
+    config,
+    error::{Error, ImportError},
+    KUBESESS_HOME,
+};
+
+use kube::config::{Kubeconfig, KubeconfigError};
+use serde::Serialize;
+use std::{
+    collections::{HashMap, HashSet},
+    io::{self, IsTerminal, Read, Write},
+    path::{Path, PathBuf},
+};
+
+/// The kubeconfig imported entries are merged into. It is picked up by the
+/// discovery like the files in `~/.kube`.
+pub fn store_path() -> PathBuf {
+    PathBuf::from(KUBESESS_HOME.as_str()).join("kubeconfig.yaml")
+}
+
+/// What happens to one entry of the imported file
+enum Change {
+    Add,
+    /// Added under another name because the original one is taken
+    Rename,
+    /// An identical entry with the same name already exists
+    Unchanged,
+}
+
+/// The entry without its name, to compare entries under different names
+fn content<T: Serialize>(entry: &T) -> Option<serde_yaml::Value> {
+    let mut value = serde_yaml::to_value(entry).ok()?;
+    if let Some(mapping) = value.as_mapping_mut() {
+        mapping.remove("name");
+    }
+    Some(value)
+}
+
+/// Works out the name an imported entry gets, and whether it needs adding.
+///
+/// When the name is taken the entry is renamed to `<name>-2`, `<name>-3` and so
+/// on, unless one of those already holds an identical entry from an earlier import.
+fn plan<T: Serialize>(
+    name: &str,
+    entry: &T,
+    existing: &HashMap<String, serde_yaml::Value>,
+    taken: &mut HashSet<String>,
+) -> (String, Change) {
+    let value = content(entry);
+    let candidates =
+        std::iter::once(name.to_string()).chain((2..).map(|i| format!("{}-{}", name, i)));
+
+    for candidate in candidates {
+        if existing
+            .get(&candidate)
+            .is_some_and(|current| Some(current) == value.as_ref())
+        {
+            return (candidate, Change::Unchanged);
+        }
+        if !taken.contains(&candidate) {
+            taken.insert(candidate.clone());
+            let change = if candidate == name {
+                Change::Add
+            } else {
+                Change::Rename
+            };
+            return (candidate, change);
+        }
+    }
+
+    unreachable!("there is always a free name")
+}
+
+fn by_name<T: Serialize>(
+    entries: &[T],
+    name: impl Fn(&T) -> &String,
+) -> HashMap<String, serde_yaml::Value> {
+    entries
+        .iter()
+        .rev()
+        .filter_map(|entry| Some((name(entry).clone(), content(entry)?)))
+        .collect()
+}
+
+fn preview(kind: &str, original: &str, name: &str, change: &Change) -> String {
+    match change {
+        Change::Add => format!("+ {} {}", kind, name),
+        Change::Rename => format!(
+            "+ {} {} (renamed from {}, name taken)",
+            kind, name, original
+        ),
+        Change::Unchanged => format!("= {} {} (already present)", kind, name),
+    }
+}
+
+/// The entry as it will be written, indented under its preview line so that the
+/// server and credentials can be checked before writing
+fn details<T: Serialize>(entry: &T) -> String {
+    serde_yaml::to_string(entry)
+        .unwrap_or_default()
+        .lines()
+        .map(|line| format!("    {}", line))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn read(file: &str) -> Result<Kubeconfig, ImportError> {
+    let read_error = |reason: String| ImportError::Read {
+        file: file.to_string(),
+        reason,
+    };
+
+    if file == "-" {
+        let mut content = String::new();
+        io::stdin()
+            .read_to_string(&mut content)
+            .map_err(|err| read_error(err.to_string()))?;
+        let mut kubeconfig =
+            Kubeconfig::from_yaml(&content).map_err(|err| read_error(err.to_string()))?;
+        // `read_from` resolves relative paths against the file, these are
+        // resolved against the directory kubesess runs in instead
+        let dir = std::env::current_dir().map_err(|err| read_error(err.to_string()))?;
+        absolutize(&mut kubeconfig, &dir);
+        Ok(kubeconfig)
+    } else {
+        Kubeconfig::read_from(file).map_err(|err| read_error(err.to_string()))
+    }
+}
+
+/// Makes the file paths of the clusters and users absolute against `dir`
+fn absolutize(kubeconfig: &mut Kubeconfig, dir: &Path) {
+    let resolve = |path: &mut Option<String>| {
+        if let Some(path) = path
+            .as_mut()
+            .filter(|path| Path::new(path.as_str()).is_relative())
+        {
+            *path = dir.join(&*path).display().to_string();
+        }
+    };
+    for cluster in kubeconfig
+        .clusters
+        .iter_mut()
+        .filter_map(|named| named.cluster.as_mut())
+    {
+        resolve(&mut cluster.certificate_authority);
+    }
+    for user in kubeconfig
+        .auth_infos
+        .iter_mut()
+        .filter_map(|named| named.auth_info.as_mut())
+    {
+        resolve(&mut user.client_certificate);
+        resolve(&mut user.client_key);
+        resolve(&mut user.token_file);
+    }
+}
+
+fn confirm(file: &str, yes: bool) -> Result<bool, ImportError> {
+    if yes {
+        return Ok(true);
+    }
+    if file == "-" || !io::stdin().is_terminal() {
+        return Err(ImportError::NotConfirmed);
+    }
+
+    let mut stderr = io::stderr();
+    write!(stderr, "Write to {}? [y/N]: ", store_path().display()).ok();
+    stderr.flush().ok();
+
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer).ok();
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
+
+/// Imports the contexts, clusters and users of `file` into the managed
+/// kubeconfig, renaming the ones whose name is already taken
+pub fn import(file: &str, prefix: Option<&str>, yes: bool, dry_run: bool) -> Result<(), Error> {
+    let incoming = read(file).map_err(Error::Import)?;
+    if incoming.contexts.is_empty()
+        && incoming.clusters.is_empty()
+        && incoming.auth_infos.is_empty()
+    {
+        return Err(Error::Import(ImportError::NothingToImport {
+            file: file.to_string(),
+        }));
+    }
+
+    let path = store_path();
+    let merged = config::get(None).config;
+    let mut store = match Kubeconfig::read_from(&path) {
+        Ok(store) => store,
+        Err(KubeconfigError::ReadConfig(err, _)) if err.kind() == io::ErrorKind::NotFound => {
+            Kubeconfig {
+                api_version: Some("v1".to_string()),
+                kind: Some("Config".to_string()),
+                ..Kubeconfig::default()
+            }
+        }
+        // Starting over would drop what was imported before
+        Err(err) => {
+            return Err(Error::Import(ImportError::Read {
+                file: path.display().to_string(),
+                reason: err.to_string(),
+            }))
+        }
+    };
+    let prefixed = |name: &str| format!("{}{}", prefix.unwrap_or_default(), name);
+
+    let mut lines = Vec::new();
+    let mut added = 0;
+
+    let existing = by_name(&merged.clusters, |x| &x.name);
+    let mut taken: HashSet<String> = existing.keys().cloned().collect();
+    let mut cluster_names = HashMap::new();
+    for cluster in incoming.clusters {
+        let mut cluster = cluster;
+        let original = cluster.name.clone();
+        cluster.name = prefixed(&original);
+        let (name, change) = plan(&cluster.name, &cluster, &existing, &mut taken);
+        lines.push(preview("cluster", &cluster.name, &name, &change));
+        cluster_names.insert(original, name.clone());
+        if !matches!(change, Change::Unchanged) {
+            cluster.name = name;
+            lines.push(details(&cluster));
+            store.clusters.push(cluster);
+            added += 1;
+        }
+    }
+
+    let existing = by_name(&merged.auth_infos, |x| &x.name);
+    let mut taken: HashSet<String> = existing.keys().cloned().collect();
+    let mut user_names = HashMap::new();
+    for auth_info in incoming.auth_infos {
+        let mut auth_info = auth_info;
+        let original = auth_info.name.clone();
+        auth_info.name = prefixed(&original);
+        let (name, change) = plan(&auth_info.name, &auth_info, &existing, &mut taken);
+        lines.push(preview("user", &auth_info.name, &name, &change));
+        user_names.insert(original, name.clone());
+        if !matches!(change, Change::Unchanged) {
+            auth_info.name = name;
+            lines.push(details(&auth_info));
+            store.auth_infos.push(auth_info);
+            added += 1;
+        }
+    }
+
+    let existing = by_name(&merged.contexts, |x| &x.name);
+    let mut taken: HashSet<String> = existing.keys().cloned().collect();
+    for context in incoming.contexts {
+        let mut context = context;
+        context.name = prefixed(&context.name);
+        if let Some(ref mut ctx) = context.context {
+            if let Some(cluster) = cluster_names.get(&ctx.cluster) {
+                ctx.cluster = cluster.clone();
+            }
+            if let Some(user) = user_names.get(&ctx.user) {
+                ctx.user = user.clone();
+            }
+        }
+
+        let (name, change) = plan(&context.name, &context, &existing, &mut taken);
+        lines.push(preview("context", &context.name, &name, &change));
+        if !matches!(change, Change::Unchanged) {
+            context.name = name;
+            lines.push(details(&context));
+            store.contexts.push(context);
+            added += 1;
+        }
+    }
+
+    for line in &lines {
+        println!("{}", line);
+    }
+
+    if added == 0 {
+        println!("Nothing to import, everything is already present");
+        return Ok(());
+    }
+    if dry_run || !confirm(file, yes).map_err(Error::Import)? {
+        println!("Nothing written");
+        return Ok(());
+    }
+
+    config::save(&store, &path);
+    println!("Imported into {}", path.display());
+    Ok(())
+}
@@ -5,181 +5,817 @@ pub enum Shell {
     Zsh,
     Fish,
     Powershell,
+    #[clap(alias = "nu")]
+    Nushell,
+    Elvish,
+    Xonsh,
+}
+
+/// Part of the init script to print on its own
+#[derive(clap::ValueEnum, Clone, Copy, PartialEq)]
+pub enum Part {
+    Functions,
+    Completions,
+    DirHook,
+}
+
+/// Names of the functions defined by the init script and which parts to print
+#[derive(clap::Args)]
+pub struct Options {
+    /// Name of the function switching the session context
+    #[clap(long, value_parser = function_name, default_value = "kc")]
+    context_cmd: String,
+    /// Name of the function switching the global context
+    #[clap(long, value_parser = function_name, default_value = "kcd")]
+    default_context_cmd: String,
+    /// Name of the function switching the session cluster
+    #[clap(long, value_parser = function_name, default_value = "kcl")]
+    cluster_cmd: String,
+    /// Name of the function switching the session user
+    #[clap(long, value_parser = function_name, default_value = "ku")]
+    user_cmd: String,
+    /// Name of the function switching the session namespace
+    #[clap(long, value_parser = function_name, default_value = "kn")]
+    namespace_cmd: String,
+    /// Name of the function switching the global namespace
+    #[clap(long, value_parser = function_name, default_value = "knd")]
+    default_namespace_cmd: String,
+    /// Prefix every function name with this
+    #[clap(long, value_parser = function_name)]
+    prefix: Option<String>,
+    /// Only print the functions, the completions for them or the directory hook
+    #[clap(long, value_enum)]
+    only: Option<Part>,
+    /// Leave out the hook switching to the context pinned by .kubesess files
+    #[clap(long, action)]
+    no_dir_hook: bool,
+}
+
+/// Function names end up unquoted in every shell's syntax, so only plain
+/// identifiers are allowed
+fn function_name(value: &str) -> Result<String, String> {
+    if !value.is_empty()
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        Ok(value.to_owned())
+    } else {
+        Err("only letters, digits, '-' and '_' are allowed".to_owned())
+    }
+}
+
+/// The parts of an init script, with `{{name}}` placeholders for the function
+/// names
+struct Template {
+    header: &'static str,
+    functions: &'static str,
+    completions: &'static str,
+    /// Switches to the context pinned by `.kubesess` files on directory changes
+    dir_hook: &'static str,
+    /// Nushell needs the completers defined before the functions using them
+    completions_first: bool,
+}
+
+impl Template {
+    fn for_shell(shell: Shell) -> Template {
+        let (header, functions, completions, dir_hook) = match shell {
+            Shell::Bash => (BASH_HEADER, BASH_FUNCTIONS, BASH_COMPLETIONS, BASH_DIR_HOOK),
+            Shell::Zsh => (ZSH_HEADER, ZSH_FUNCTIONS, ZSH_COMPLETIONS, ZSH_DIR_HOOK),
+            Shell::Fish => (FISH_HEADER, FISH_FUNCTIONS, FISH_COMPLETIONS, FISH_DIR_HOOK),
+            Shell::Powershell => (
+                POWERSHELL_HEADER,
+                POWERSHELL_FUNCTIONS,
+                POWERSHELL_COMPLETIONS,
+                POWERSHELL_DIR_HOOK,
+            ),
+            Shell::Nushell => (
+                NUSHELL_HEADER,
+                NUSHELL_FUNCTIONS,
+                NUSHELL_COMPLETIONS,
+                NUSHELL_DIR_HOOK,
+            ),
+            Shell::Elvish => (
+                ELVISH_HEADER,
+                ELVISH_FUNCTIONS,
+                ELVISH_COMPLETIONS,
+                ELVISH_DIR_HOOK,
+            ),
+            Shell::Xonsh => (
+                XONSH_HEADER,
+                XONSH_FUNCTIONS,
+                XONSH_COMPLETIONS,
+                XONSH_DIR_HOOK,
+            ),
+        };
+        Template {
+            header,
+            functions,
+            completions,
+            dir_hook,
+            completions_first: matches!(shell, Shell::Nushell),
+        }
+    }
+
+    fn render(&self, options: &Options) -> String {
+        let included = |part| options.only.is_none_or(|only| only == part);
+        let completions = included(Part::Completions);
+
+        let mut parts = vec![(true, self.header)];
+        if self.completions_first {
+            parts.extend([
+                (completions, self.completions),
+                (included(Part::Functions), self.functions),
+            ]);
+        } else {
+            parts.extend([
+                (included(Part::Functions), self.functions),
+                (completions, self.completions),
+            ]);
+        }
+        parts.push((
+            included(Part::DirHook) && (options.only.is_some() || !options.no_dir_hook),
+            self.dir_hook,
+        ));
+        let parts: Vec<&str> = parts
+            .into_iter()
+            .filter(|(include, part)| *include && !part.is_empty())
+            .map(|(_, part)| part)
+            .collect();
+
+        let prefix = options.prefix.as_deref().unwrap_or_default();
+        let mut script = parts.join("\n");
+        for (placeholder, name) in [
+            ("{{kcd}}", &options.default_context_cmd),
+            ("{{kcl}}", &options.cluster_cmd),
+            ("{{knd}}", &options.default_namespace_cmd),
+            ("{{kc}}", &options.context_cmd),
+            ("{{ku}}", &options.user_cmd),
+            ("{{kn}}", &options.namespace_cmd),
+        ] {
+            script = script.replace(placeholder, &format!("{}{}", prefix, name));
+        }
+        // Without the completers defined, nushell would reject the references
+        for (placeholder, completer) in [
+            (
+                "{{contexts_completer}}",
+                "@\"nu-complete kubesess contexts\"",
+            ),
+            (
+                "{{namespaces_completer}}",
+                "@\"nu-complete kubesess namespaces\"",
+            ),
+        ] {
+            script = script.replace(placeholder, if completions { completer } else { "" });
+        }
+        script
+    }
 }
 
 /// Print the shell initialization script for the given shell
-pub fn print_init_script(shell: Shell) {
-    let script = match shell {
-        Shell::Bash | Shell::Zsh => BASH_ZSH_INIT,
-        Shell::Fish => FISH_INIT,
-        Shell::Powershell => POWERSHELL_INIT,
-    };
-    print!("{}", script);
+pub fn print_init_script(shell: Shell, options: &Options) {
+    print!("{}", Template::for_shell(shell).render(options));
 }
 
-const BASH_ZSH_INIT: &str = r#"# kubesess shell integration
-# Add to your .bashrc or .zshrc:
-#   eval "$(kubesess init bash)"  # for bash
-#   eval "$(kubesess init zsh)"   # for zsh
+const BASH_HEADER: &str = r#"# kubesess shell integration for bash
+# Add to your .bashrc:
+#   eval "$(kubesess init bash)"
+"#;
 
-__kubesess_export() {
+const BASH_FUNCTIONS: &str = r#"__kubesess_export() {
   local OUTPUT
   OUTPUT="$(kubesess "$@")" || return $?
   export KUBECONFIG="$OUTPUT"
 }
 
-kc() {
-  __kubesess_export context ${1:+"-v" "$1"}
+# The first argument is the value to switch to, unless it is an option like --tag
+__kubesess_switch() {
+  local CMD="$1"
+  shift
+  # An empty value, as in `ku "" --as jane`, means none was given
+  if [[ $# -gt 0 && -z "$1" ]]; then
+    shift
+  fi
+  if [[ -n "$1" && "$1" != -* ]]; then
+    __kubesess_export "$CMD" -v "$1" "${@:2}"
+  else
+    __kubesess_export "$CMD" "$@"
+  fi
 }
 
-kcd() {
-  __kubesess_export default-context ${1:+"-v" "$1"}
+{{kc}}() {
+  __kubesess_switch context "$@"
 }
 
-kn() {
-  __kubesess_export namespace ${1:+"-v" "$1"}
+{{kcd}}() {
+  __kubesess_switch default-context "$@"
 }
 
-knd() {
-  __kubesess_export default-namespace ${1:+"-v" "$1"}
+{{kcl}}() {
+  __kubesess_switch cluster "$@"
+}
+
+{{ku}}() {
+  __kubesess_switch user "$@"
+}
+
+{{kn}}() {
+  __kubesess_switch namespace "$@"
+}
+
+{{knd}}() {
+  __kubesess_switch default-namespace "$@"
+}
+"#;
+
+const BASH_COMPLETIONS: &str = r#"# Completions
+# kubesess prints one name and description per line, separated by a tab.
+# It already filters by substring and fuzzy score, so the names are used as is.
+__kubesess_complete() {
+  local IFS=$'\n'
+  COMPREPLY=($(kubesess "$1" --describe -v "$2" 2>/dev/null | cut -f1))
 }
 
-# Completions
 _kc_completions() {
-  COMPREPLY=($(compgen -W "$(kubesess completion-context -v "${COMP_WORDS[1]}")" -- "${COMP_WORDS[1]}"))
+  __kubesess_complete completion-context "${COMP_WORDS[1]}"
 }
 
 _kn_completions() {
-  COMPREPLY=($(compgen -W "$(kubesess completion-namespace -v "${COMP_WORDS[1]}")" -- "${COMP_WORDS[1]}"))
+  __kubesess_complete completion-namespace "${COMP_WORDS[1]}"
+}
+
+complete -F _kc_completions {{kc}}
+complete -F _kc_completions {{kcd}}
+complete -F _kn_completions {{kn}}
+complete -F _kn_completions {{knd}}
+"#;
+
+const BASH_DIR_HOOK: &str = r#"# Switches to the context pinned by the nearest trusted .kubesess file when the
+# directory changes, and back to the previous session when leaving it
+__kubesess_dir_hook() {
+  [[ "$PWD" == "$__KUBESESS_PWD" ]] && return
+  __KUBESESS_PWD="$PWD"
+  local output pin
+  output="$(kubesess dir hook)" || return
+  pin="${output%%$'\n'*}"
+  if [[ -n "$pin" ]]; then
+    [[ "$pin" == "$__KUBESESS_PIN" ]] && return
+    [[ -z "$__KUBESESS_PIN" ]] && __KUBESESS_PREVIOUS="$KUBECONFIG"
+    __KUBESESS_PIN="$pin"
+    export KUBECONFIG="${output#*$'\n'}"
+  elif [[ -n "$__KUBESESS_PIN" ]]; then
+    if [[ -n "$__KUBESESS_PREVIOUS" ]]; then
+      export KUBECONFIG="$__KUBESESS_PREVIOUS"
+    else
+      unset KUBECONFIG
+    fi
+    unset __KUBESESS_PIN __KUBESESS_PREVIOUS
+  fi
 }
 
-complete -F _kc_completions kc
-complete -F _kc_completions kcd
-complete -F _kn_completions kn
-complete -F _kn_completions knd
+if [[ ";$PROMPT_COMMAND;" != *";__kubesess_dir_hook;"* ]]; then
+  PROMPT_COMMAND="__kubesess_dir_hook${PROMPT_COMMAND:+;$PROMPT_COMMAND}"
+fi
 "#;
 
-const FISH_INIT: &str = r#"# kubesess shell integration for fish
+const ZSH_HEADER: &str = r#"# kubesess shell integration for zsh
+# Add to your .zshrc, after compinit:
+#   eval "$(kubesess init zsh)"
+"#;
+
+const ZSH_FUNCTIONS: &str = r#"__kubesess_export() {
+  local output
+  output="$(kubesess "$@")" || return $?
+  export KUBECONFIG="$output"
+}
+
+# The first argument is the value to switch to, unless it is an option like --tag
+__kubesess_switch() {
+  local cmd="$1"
+  shift
+  # An empty value, as in `ku "" --as jane`, means none was given
+  if [[ $# -gt 0 && -z "$1" ]]; then
+    shift
+  fi
+  if [[ -n "$1" && "$1" != -* ]]; then
+    __kubesess_export "$cmd" -v "$1" "${@[2,-1]}"
+  else
+    __kubesess_export "$cmd" "$@"
+  fi
+}
+
+{{kc}}() { __kubesess_switch context "$@" }
+{{kcd}}() { __kubesess_switch default-context "$@" }
+{{kcl}}() { __kubesess_switch cluster "$@" }
+{{ku}}() { __kubesess_switch user "$@" }
+{{kn}}() { __kubesess_switch namespace "$@" }
+{{knd}}() { __kubesess_switch default-namespace "$@" }
+"#;
+
+const ZSH_COMPLETIONS: &str = r#"# Completions
+# kubesess prints one name and description per line, separated by a tab.
+# _describe splits on the first unescaped colon, so colons in names are escaped.
+__kubesess_describe() {
+  local tag="$1" description="$2" line
+  local -a candidates
+  shift 2
+  for line in ${(f)"$(kubesess "$@" --describe 2>/dev/null)"}; do
+    candidates+=("${${line%%$'\t'*}//:/\\:}:${line#*$'\t'}")
+  done
+  _describe -t "$tag" "$description" candidates
+}
+
+_kubesess_contexts() {
+  (( CURRENT == 2 )) || return 1
+  __kubesess_describe contexts 'kubernetes context' completion-context
+}
+
+_kubesess_namespaces() {
+  (( CURRENT == 2 )) || return 1
+  __kubesess_describe namespaces 'kubernetes namespace' completion-namespace
+}
+
+if (( $+functions[compdef] )); then
+  compdef _kubesess_contexts {{kc}} {{kcd}}
+  compdef _kubesess_namespaces {{kn}} {{knd}}
+fi
+"#;
+
+const ZSH_DIR_HOOK: &str = r#"# Switches to the context pinned by the nearest trusted .kubesess file when the
+# directory changes, and back to the previous session when leaving it
+__kubesess_dir_hook() {
+  local output pin
+  output="$(kubesess dir hook)" || return
+  pin="${output%%$'\n'*}"
+  if [[ -n "$pin" ]]; then
+    [[ "$pin" == "$__KUBESESS_PIN" ]] && return
+    [[ -z "$__KUBESESS_PIN" ]] && __KUBESESS_PREVIOUS="$KUBECONFIG"
+    __KUBESESS_PIN="$pin"
+    export KUBECONFIG="${output#*$'\n'}"
+  elif [[ -n "$__KUBESESS_PIN" ]]; then
+    if [[ -n "$__KUBESESS_PREVIOUS" ]]; then
+      export KUBECONFIG="$__KUBESESS_PREVIOUS"
+    else
+      unset KUBECONFIG
+    fi
+    unset __KUBESESS_PIN __KUBESESS_PREVIOUS
+  fi
+}
+
+autoload -Uz add-zsh-hook
+add-zsh-hook chpwd __kubesess_dir_hook
+__kubesess_dir_hook
+"#;
+
+const FISH_HEADER: &str = r#"# kubesess shell integration for fish
 # Add to your config.fish:
 #   kubesess init fish | source
+"#;
 
-function kc --argument-names context --description "Switch current kubernetes context"
-    set -l cmd kubesess context
-    if test -n "$argv"
-        set -a cmd -v $context
+const FISH_FUNCTIONS: &str = r#"# The first argument is the value to switch to, unless it is an option like --tag
+function __kubesess_switch
+    set -l cmd kubesess $argv[1]
+    set -l args $argv[2..-1]
+    # An empty value, as in `ku "" --as jane`, means none was given
+    if set -q args[1]; and test -z "$args[1]"
+        set -e args[1]
+    end
+    if test -n "$args[1]"; and not string match -q -- '-*' $args[1]
+        set -a cmd -v $args[1]
+        set -e args[1]
     end
-    set -l config (command $cmd) || return $status
+    set -l config (command $cmd $args) || return $status
     set -gx KUBECONFIG $config
 end
 
-function kcd --argument-names context --description "Switch global kubernetes context"
-    set -l cmd kubesess default-context
-    if test -n "$argv"
-        set -a cmd -v $context
-    end
-    set -l config (command $cmd) || return $status
-    set -gx KUBECONFIG $config
+function {{kc}} --description "Switch current kubernetes context"
+    __kubesess_switch context $argv
 end
 
-function kn --argument-names namespace --description "Switch current kubernetes namespace"
-    set -l cmd kubesess namespace
-    if test -n "$argv"
-        set -a cmd -v $namespace
-    end
-    set -l config (command $cmd) || return $status
-    set -gx KUBECONFIG $config
+function {{kcd}} --description "Switch global kubernetes context"
+    __kubesess_switch default-context $argv
 end
 
-function knd --argument-names namespace --description "Switch global kubernetes namespace"
-    set -l cmd kubesess default-namespace
-    if test -n "$argv"
-        set -a cmd -v $namespace
-    end
-    set -l config (command $cmd) || return $status
-    set -gx KUBECONFIG $config
+function {{kcl}} --description "Switch current kubernetes cluster"
+    __kubesess_switch cluster $argv
+end
+
+function {{ku}} --description "Switch current kubernetes user"
+    __kubesess_switch user $argv
 end
 
-# Completions for kc/kcd (context)
-function __kubesess_contexts
-    kubectl config get-contexts -o name 2>/dev/null
+function {{kn}} --description "Switch current kubernetes namespace"
+    __kubesess_switch namespace $argv
 end
 
-complete -c kc -f -a '(__kubesess_contexts)'
-complete -c kcd -f -a '(__kubesess_contexts)'
+function {{knd}} --description "Switch global kubernetes namespace"
+    __kubesess_switch default-namespace $argv
+end
+"#;
 
-# Completions for kn/knd (namespace)
-function __kubesess_namespaces
-    kubectl get ns --no-headers -o custom-columns=":metadata.name" 2>/dev/null
+const FISH_COMPLETIONS: &str = r#"# Completions
+# kubesess prints one name and description per line, separated by a tab, as fish expects
+complete -c {{kc}} -f -a '(kubesess completion-context --describe 2>/dev/null)'
+complete -c {{kcd}} -f -a '(kubesess completion-context --describe 2>/dev/null)'
+complete -c {{kn}} -f -a '(kubesess completion-namespace --describe 2>/dev/null)'
+complete -c {{knd}} -f -a '(kubesess completion-namespace --describe 2>/dev/null)'
+"#;
+
+const FISH_DIR_HOOK: &str = r#"# Switches to the context pinned by the nearest trusted .kubesess file when the
+# directory changes, and back to the previous session when leaving it
+function __kubesess_dir_hook --on-variable PWD
+    set -l output (kubesess dir hook); or return
+    if test -n "$output[1]"
+        test "$output[1]" = "$__KUBESESS_PIN"; and return
+        test -z "$__KUBESESS_PIN"; and set -g __KUBESESS_PREVIOUS $KUBECONFIG
+        set -g __KUBESESS_PIN $output[1]
+        set -gx KUBECONFIG $output[2]
+    else if test -n "$__KUBESESS_PIN"
+        if test -n "$__KUBESESS_PREVIOUS"
+            set -gx KUBECONFIG $__KUBESESS_PREVIOUS
+        else
+            set -e KUBECONFIG
+        end
+        set -e __KUBESESS_PIN __KUBESESS_PREVIOUS
+    end
 end
 
-complete -c kn -f -a '(__kubesess_namespaces)'
-complete -c knd -f -a '(__kubesess_namespaces)'
+__kubesess_dir_hook
 "#;
 
-const POWERSHELL_INIT: &str = r#"# kubesess shell integration for PowerShell
+const POWERSHELL_HEADER: &str = r#"# kubesess shell integration for PowerShell
 # Add to your PowerShell profile:
 #   Invoke-Expression (&kubesess init powershell)
+"#;
 
-function kc {
-    param([string]$Context)
-    if ($Context) {
-        $config = kubesess context -v $Context
-    } else {
-        $config = kubesess context
+const POWERSHELL_FUNCTIONS: &str = r#"# The first argument is the value to switch to, unless it is an option like --tag
+function __kubesess_switch {
+    param([string]$Command, [string[]]$Arguments)
+    # Values that weren't given are bound as empty strings
+    $Arguments = @($Arguments | Where-Object { $_ })
+    if ($Arguments -and -not $Arguments[0].StartsWith('-')) {
+        $Arguments = @('-v') + $Arguments
     }
+    $config = kubesess $Command @Arguments
     if ($LASTEXITCODE -eq 0) {
         $env:KUBECONFIG = $config
     }
 }
 
-function kcd {
-    param([string]$Context)
-    if ($Context) {
-        $config = kubesess default-context -v $Context
-    } else {
-        $config = kubesess default-context
-    }
-    if ($LASTEXITCODE -eq 0) {
-        $env:KUBECONFIG = $config
+function {{kc}} {
+    param([string]$Context, [Parameter(ValueFromRemainingArguments)][string[]]$Rest)
+    __kubesess_switch context (@($Context) + $Rest)
+}
+
+function {{kcd}} {
+    param([string]$Context, [Parameter(ValueFromRemainingArguments)][string[]]$Rest)
+    __kubesess_switch default-context (@($Context) + $Rest)
+}
+
+function {{kcl}} {
+    param([string]$Cluster, [Parameter(ValueFromRemainingArguments)][string[]]$Rest)
+    __kubesess_switch cluster (@($Cluster) + $Rest)
+}
+
+function {{ku}} {
+    param([string]$User, [Parameter(ValueFromRemainingArguments)][string[]]$Rest)
+    __kubesess_switch user (@($User) + $Rest)
+}
+
+function {{kn}} {
+    param([string]$Namespace, [Parameter(ValueFromRemainingArguments)][string[]]$Rest)
+    __kubesess_switch namespace (@($Namespace) + $Rest)
+}
+
+function {{knd}} {
+    param([string]$Namespace, [Parameter(ValueFromRemainingArguments)][string[]]$Rest)
+    __kubesess_switch default-namespace (@($Namespace) + $Rest)
+}
+"#;
+
+const POWERSHELL_COMPLETIONS: &str = r#"# Tab completions
+# kubesess prints one name and description per line, separated by a tab.
+# It already filters by substring and fuzzy score, so the names are used as is.
+function __kubesess_complete {
+    param([string]$Completion, [string]$WordToComplete)
+    kubesess $Completion --describe -v $WordToComplete 2>$null | ForEach-Object {
+        $name, $description = $_ -split "`t", 2
+        [System.Management.Automation.CompletionResult]::new($name, $name, 'ParameterValue', $description)
     }
 }
 
-function kn {
-    param([string]$Namespace)
-    if ($Namespace) {
-        $config = kubesess namespace -v $Namespace
-    } else {
-        $config = kubesess namespace
+Register-ArgumentCompleter -CommandName {{kc}}, {{kcd}} -ParameterName Context -ScriptBlock {
+    param($commandName, $parameterName, $wordToComplete, $commandAst, $fakeBoundParameters)
+    __kubesess_complete completion-context $wordToComplete
+}
+
+Register-ArgumentCompleter -CommandName {{kn}}, {{knd}} -ParameterName Namespace -ScriptBlock {
+    param($commandName, $parameterName, $wordToComplete, $commandAst, $fakeBoundParameters)
+    __kubesess_complete completion-namespace $wordToComplete
+}
+"#;
+
+const POWERSHELL_DIR_HOOK: &str = r#"# Switches to the context pinned by the nearest trusted .kubesess file when the
+# directory changes, and back to the previous session when leaving it
+function __kubesess_dir_hook {
+    if ($PWD.Path -eq $global:__KubesessPwd) { return }
+    $global:__KubesessPwd = $PWD.Path
+    $output = @(kubesess dir hook)
+    if ($LASTEXITCODE -ne 0) { return }
+    if ($output.Count -eq 2) {
+        if ($output[0] -eq $global:__KubesessPin) { return }
+        if (-not $global:__KubesessPin) { $global:__KubesessPrevious = $env:KUBECONFIG }
+        $global:__KubesessPin = $output[0]
+        $env:KUBECONFIG = $output[1]
+    } elseif ($global:__KubesessPin) {
+        $env:KUBECONFIG = $global:__KubesessPrevious
+        $global:__KubesessPin = $null
+        $global:__KubesessPrevious = $null
     }
-    if ($LASTEXITCODE -eq 0) {
-        $env:KUBECONFIG = $config
+}
+
+if (-not $global:__KubesessPrompt) {
+    $global:__KubesessPrompt = $function:prompt
+    function global:prompt {
+        __kubesess_dir_hook
+        & $global:__KubesessPrompt
     }
 }
+"#;
+
+const NUSHELL_HEADER: &str = r#"# kubesess shell integration for nushell
+# Save it next to your config.nu (again after upgrading kubesess):
+#   kubesess init nushell | save -f ($nu.default-config-dir | path join kubesess.nu)
+# Then add to your config.nu:
+#   source kubesess.nu
+"#;
 
-function knd {
-    param([string]$Namespace)
-    if ($Namespace) {
-        $config = kubesess default-namespace -v $Namespace
+const NUSHELL_FUNCTIONS: &str = r#"# The first argument is the value to switch to, unless it is an option like --tag
+def --env __kubesess_switch [command: string, args: list<string>] {
+    # An empty value, as in `ku "" --as jane`, means none was given
+    let args = if ($args | is-not-empty) and ($args | first | is-empty) { $args | skip 1 } else { $args }
+    let args = if ($args | is-empty) or ($args | first | str starts-with "-") {
+        $args
     } else {
-        $config = kubesess default-namespace
-    }
-    if ($LASTEXITCODE -eq 0) {
-        $env:KUBECONFIG = $config
+        ["-v"] ++ $args
     }
+    $env.KUBECONFIG = (^kubesess $command ...$args | str trim)
 }
 
-# Tab completions
-Register-ArgumentCompleter -CommandName kc, kcd -ParameterName Context -ScriptBlock {
-    param($commandName, $parameterName, $wordToComplete, $commandAst, $fakeBoundParameters)
-    kubectl config get-contexts -o name 2>$null | Where-Object { $_ -like "$wordToComplete*" } | ForEach-Object {
-        [System.Management.Automation.CompletionResult]::new($_, $_, 'ParameterValue', $_)
+# Switch current kubernetes context
+def --env --wrapped {{kc}} [...args: string{{contexts_completer}}] {
+    __kubesess_switch context $args
+}
+
+# Switch global kubernetes context
+def --env --wrapped {{kcd}} [...args: string{{contexts_completer}}] {
+    __kubesess_switch default-context $args
+}
+
+# Switch current kubernetes cluster
+def --env --wrapped {{kcl}} [...args: string] {
+    __kubesess_switch cluster $args
+}
+
+# Switch current kubernetes user
+def --env --wrapped {{ku}} [...args: string] {
+    __kubesess_switch user $args
+}
+
+# Switch current kubernetes namespace
+def --env --wrapped {{kn}} [...args: string{{namespaces_completer}}] {
+    __kubesess_switch namespace $args
+}
+
+# Switch global kubernetes namespace
+def --env --wrapped {{knd}} [...args: string{{namespaces_completer}}] {
+    __kubesess_switch default-namespace $args
+}
+"#;
+
+const NUSHELL_COMPLETIONS: &str = r#"# kubesess prints one name and description per line, separated by a tab
+def "nu-complete kubesess contexts" [] {
+    ^kubesess completion-context --describe | lines | parse "{value}\t{description}"
+}
+
+def "nu-complete kubesess namespaces" [] {
+    ^kubesess completion-namespace --describe | lines | parse "{value}\t{description}"
+}
+"#;
+
+const NUSHELL_DIR_HOOK: &str = r#"# Switches to the context pinned by the nearest trusted .kubesess file when the
+# directory changes, and back to the previous session when leaving it
+def --env __kubesess_dir_hook [] {
+    let result = (^kubesess dir hook | complete)
+    if $result.exit_code != 0 { return }
+    let output = ($result.stdout | lines)
+    let pin = ($output.0? | default "")
+    let current = ($env.__KUBESESS_PIN? | default "")
+    if ($pin | is-not-empty) {
+        if $pin == $current { return }
+        if ($current | is-empty) { $env.__KUBESESS_PREVIOUS = ($env.KUBECONFIG? | default "") }
+        $env.__KUBESESS_PIN = $pin
+        $env.KUBECONFIG = $output.1
+    } else if ($current | is-not-empty) {
+        if ($env.__KUBESESS_PREVIOUS | is-not-empty) {
+            $env.KUBECONFIG = $env.__KUBESESS_PREVIOUS
+        } else {
+            hide-env -i KUBECONFIG
+        }
+        hide-env -i __KUBESESS_PIN __KUBESESS_PREVIOUS
     }
 }
 
-Register-ArgumentCompleter -CommandName kn, knd -ParameterName Namespace -ScriptBlock {
-    param($commandName, $parameterName, $wordToComplete, $commandAst, $fakeBoundParameters)
-    kubectl get ns --no-headers -o custom-columns=":metadata.name" 2>$null | Where-Object { $_ -like "$wordToComplete*" } | ForEach-Object {
-        [System.Management.Automation.CompletionResult]::new($_, $_, 'ParameterValue', $_)
+$env.config.hooks.env_change.PWD = (
+    ($env.config.hooks.env_change.PWD? | default []) ++ [{|before, after| __kubesess_dir_hook }]
+)
+__kubesess_dir_hook
+"#;
+
+const ELVISH_HEADER: &str = r#"# kubesess shell integration for elvish
+# Add to your rc.elv:
+#   eval (kubesess init elvish | slurp)
+
+use str
+"#;
+
+const ELVISH_FUNCTIONS: &str = r#"# The first argument is the value to switch to, unless it is an option like --tag
+fn __kubesess-switch {|cmd @args|
+  # An empty value, as in `ku "" --as jane`, means none was given
+  if (and (> (count $args) 0) (eq $args[0] '')) {
+    set args = $args[1..]
+  }
+  if (and (> (count $args) 0) (not (str:has-prefix $args[0] -))) {
+    set args = [-v $@args]
+  }
+  set-env KUBECONFIG (e:kubesess $cmd $@args)
+}
+
+fn {{kc}} {|@args| __kubesess-switch context $@args }
+fn {{kcd}} {|@args| __kubesess-switch default-context $@args }
+fn {{kcl}} {|@args| __kubesess-switch cluster $@args }
+fn {{ku}} {|@args| __kubesess-switch user $@args }
+fn {{kn}} {|@args| __kubesess-switch namespace $@args }
+fn {{knd}} {|@args| __kubesess-switch default-namespace $@args }
+
+edit:add-var {{kc}}~ ${{kc}}~
+edit:add-var {{kcd}}~ ${{kcd}}~
+edit:add-var {{kcl}}~ ${{kcl}}~
+edit:add-var {{ku}}~ ${{ku}}~
+edit:add-var {{kn}}~ ${{kn}}~
+edit:add-var {{knd}}~ ${{knd}}~
+"#;
+
+const ELVISH_COMPLETIONS: &str = r#"# Completions
+# kubesess prints one name and description per line, separated by a tab.
+# It already filters by substring and fuzzy score, so the names are used as is.
+fn __kubesess-candidates {|@completion|
+  e:kubesess $@completion --describe | from-lines | each {|line|
+    var name description = (str:split "\t" $line)
+    edit:complex-candidate $name &display=$name' '$description
+  }
+}
+fn __kubesess-contexts {|@words| __kubesess-candidates completion-context -v $words[-1] }
+fn __kubesess-namespaces {|@words| __kubesess-candidates completion-namespace -v $words[-1] }
+
+set edit:completion:arg-completer[{{kc}}] = $__kubesess-contexts~
+set edit:completion:arg-completer[{{kcd}}] = $__kubesess-contexts~
+set edit:completion:arg-completer[{{kn}}] = $__kubesess-namespaces~
+set edit:completion:arg-completer[{{knd}}] = $__kubesess-namespaces~
+"#;
+
+const ELVISH_DIR_HOOK: &str = r#"# Switches to the context pinned by the nearest trusted .kubesess file when the
+# directory changes, and back to the previous session when leaving it
+var __kubesess-pin = ''
+var __kubesess-previous = ''
+fn __kubesess-dir-hook {|@_|
+  var output = []
+  if (not ?(set output = [(e:kubesess dir hook)])) {
+    return
+  }
+  if (> (count $output) 1) {
+    if (eq $output[0] $__kubesess-pin) {
+      return
     }
+    if (eq $__kubesess-pin '') {
+      set __kubesess-previous = ''
+      if (has-env KUBECONFIG) {
+        set __kubesess-previous = (get-env KUBECONFIG)
+      }
+    }
+    set __kubesess-pin = $output[0]
+    set-env KUBECONFIG $output[1]
+  } elif (not-eq $__kubesess-pin '') {
+    if (not-eq $__kubesess-previous '') {
+      set-env KUBECONFIG $__kubesess-previous
+    } else {
+      unset-env KUBECONFIG
+    }
+    set __kubesess-pin = ''
+    set __kubesess-previous = ''
+  }
+}
+
+set after-chdir = [$@after-chdir $__kubesess-dir-hook~]
+__kubesess-dir-hook
+"#;
+
+const XONSH_HEADER: &str = r#"# kubesess shell integration for xonsh
+# Add to your .xonshrc:
+#   execx($(kubesess init xonsh))
+
+import subprocess as _kubesess_subprocess
+
+from xonsh.completers.completer import add_one_completer as _kubesess_add_completer
+from xonsh.completers.tools import RichCompletion as _KubesessCompletion
+from xonsh.completers.tools import contextual_command_completer as _kubesess_completer
+"#;
+
+const XONSH_FUNCTIONS: &str = r#"
+def _kubesess_switch(command, args):
+    """The first argument is the value to switch to, unless it is an option like --tag"""
+    args = list(args)
+    # An empty value, as in `ku "" --as jane`, means none was given
+    if args and not args[0]:
+        args = args[1:]
+    if args and not args[0].startswith("-"):
+        args = ["-v"] + args
+    result = _kubesess_subprocess.run(
+        ["kubesess", command, *args], stdout=_kubesess_subprocess.PIPE, text=True
+    )
+    if result.returncode == 0:
+        __xonsh__.env["KUBECONFIG"] = result.stdout.strip()
+    return result.returncode
+
+
+aliases["{{kc}}"] = lambda args: _kubesess_switch("context", args)
+aliases["{{kcd}}"] = lambda args: _kubesess_switch("default-context", args)
+aliases["{{kcl}}"] = lambda args: _kubesess_switch("cluster", args)
+aliases["{{ku}}"] = lambda args: _kubesess_switch("user", args)
+aliases["{{kn}}"] = lambda args: _kubesess_switch("namespace", args)
+aliases["{{knd}}"] = lambda args: _kubesess_switch("default-namespace", args)
+"#;
+
+const XONSH_COMPLETIONS: &str = r#"# Completions
+# kubesess prints one name and description per line, separated by a tab.
+# It already filters by substring and fuzzy score, so the names are used as is.
+_KUBESESS_COMPLETIONS = {
+    "{{kc}}": "completion-context",
+    "{{kcd}}": "completion-context",
+    "{{kn}}": "completion-namespace",
+    "{{knd}}": "completion-namespace",
 }
+
+
+@_kubesess_completer
+def _kubesess_complete(context):
+    completion = _KUBESESS_COMPLETIONS.get(context.args[0].value if context.args else None)
+    if completion is None or context.arg_index != 1:
+        return None
+    result = _kubesess_subprocess.run(
+        ["kubesess", completion, "--describe", "-v", context.prefix],
+        stdout=_kubesess_subprocess.PIPE,
+        text=True,
+    )
+    candidates = set()
+    for line in result.stdout.splitlines():
+        name, _, description = line.partition("\t")
+        candidates.add(_KubesessCompletion(name, description=description))
+    return candidates
+
+
+_kubesess_add_completer("kubesess", _kubesess_complete, "start")
+"#;
+
+const XONSH_DIR_HOOK: &str = r#"
+_KUBESESS_PIN = {"pin": None, "previous": None}
+
+
+@events.on_chdir
+def _kubesess_dir_hook(**kwargs):
+    """Switches to the context pinned by the nearest trusted .kubesess file when the
+    directory changes, and back to the previous session when leaving it"""
+    result = _kubesess_subprocess.run(
+        ["kubesess", "dir", "hook"], stdout=_kubesess_subprocess.PIPE, text=True
+    )
+    if result.returncode != 0:
+        return
+    output = result.stdout.splitlines()
+    env = __xonsh__.env
+    if output:
+        if output[0] == _KUBESESS_PIN["pin"]:
+            return
+        if _KUBESESS_PIN["pin"] is None:
+            _KUBESESS_PIN["previous"] = env.get("KUBECONFIG")
+        _KUBESESS_PIN["pin"] = output[0]
+        env["KUBECONFIG"] = output[1]
+    elif _KUBESESS_PIN["pin"] is not None:
+        if _KUBESESS_PIN["previous"]:
+            env["KUBECONFIG"] = _KUBESESS_PIN["previous"]
+        elif "KUBECONFIG" in env:
+            del env["KUBECONFIG"]
+        _KUBESESS_PIN.update(pin=None, previous=None)
+
+
+_kubesess_dir_hook()
 "#;
@@ -0,0 +1,74 @@
+use crate::{config, SETTINGS};
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+#[derive(clap::ValueEnum, Clone, Copy)]
+pub enum Format {
+    Text,
+    Json,
+}
+
+#[derive(Serialize)]
+struct Entry {
+    name: String,
+    cluster: String,
+    user: String,
+    namespace: Option<String>,
+    file: String,
+    tags: BTreeMap<String, String>,
+}
+
+/// Prints the contexts with all of `tags`
+pub fn list(tags: &[String], format: Format) {
+    let configs = config::get(None);
+    let entries: Vec<Entry> = configs
+        .context_options()
+        .into_iter()
+        .filter(|option| SETTINGS.has_tags(&option.name, tags))
+        .map(|option| {
+            let namespace = configs
+                .configs
+                .iter()
+                .find(|(_, path)| *path == option.path)
+                .and_then(|(kubeconfig, _)| {
+                    kubeconfig
+                        .contexts
+                        .iter()
+                        .find(|context| context.name == option.name)
+                })
+                .and_then(|context| context.context.as_ref())
+                .and_then(|context| context.namespace.clone());
+
+            Entry {
+                tags: SETTINGS.tags(&option.name),
+                name: option.name,
+                cluster: option.cluster,
+                user: option.user,
+                namespace,
+                file: option.path.display().to_string(),
+            }
+        })
+        .collect();
+
+    match format {
+        Format::Json => println!("{}", serde_json::to_string_pretty(&entries).unwrap()),
+        Format::Text => {
+            for entry in entries {
+                let tags: Vec<String> = entry
+                    .tags
+                    .iter()
+                    .map(|(key, value)| format!("{}={}", key, value))
+                    .collect();
+                println!(
+                    "{}\t{}\t{}\t{}\t{}",
+                    entry.name,
+                    entry.cluster,
+                    entry.user,
+                    entry.namespace.as_deref().unwrap_or("default"),
+                    tags.join(",")
+                );
+            }
+        }
+    }
+}
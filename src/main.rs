@@ -1,8 +1,21 @@
+mod check;
 mod commands;
+mod completions;
 mod config;
+mod edit;
 mod error;
+mod export;
+mod import;
 mod init;
+mod list;
+mod matching;
 mod modes;
+mod namespaces;
+mod picker;
+mod pin;
+mod settings;
+mod state;
+mod status;
 
 use clap::{Parser, Subcommand};
 use kube::config::Kubeconfig;
@@ -10,7 +23,7 @@ use std::collections::HashSet;
 use std::env;
 use std::fs;
 use std::io;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process;
 
 #[macro_use]
@@ -59,6 +72,12 @@ lazy_static! {
             }
         }
 
+        // The kubeconfig `kubesess import` writes to
+        let store = import::store_path().to_string_lossy().into_owned();
+        if Path::new(&store).is_file() && paths_set.insert(store.clone()) {
+            all_paths.push(store);
+        }
+
         all_paths.join(":")
     };
     static ref KUBESESSCONFIG: String = {
@@ -75,10 +94,12 @@ lazy_static! {
             Err(_e) => "".to_string(),
         }
     };
-    static ref DEST: String = format!(
-        "{}/.kube/kubesess/cache",
+    static ref KUBESESS_HOME: String = format!(
+        "{}/.kube/kubesess",
         dirs::home_dir().unwrap().display()
     );
+    static ref DEST: String = format!("{}/cache", KUBESESS_HOME.as_str());
+    static ref SETTINGS: settings::Settings = settings::load();
 }
 
 #[derive(Parser)]
@@ -99,64 +120,322 @@ pub struct ModeArgs {
     pub current: bool,
 }
 
+/// Options narrowing down the namespaces to pick from
+#[derive(clap::Args, Clone)]
+pub struct NamespaceFilter {
+    /// Only offer namespaces matching this label selector, e.g. team=payments
+    #[clap(short = 'l', long, value_parser)]
+    pub selector: Option<String>,
+    /// Also offer the namespaces hidden by the kubesess config
+    #[clap(short, long, action)]
+    pub all: bool,
+}
+
 #[derive(Subcommand)]
 enum Command {
     /// Switch to a context (session-specific)
+    #[clap(args_conflicts_with_subcommands = true)]
     Context {
         #[clap(flatten)]
         args: ModeArgs,
+        /// Ignore the namespace last used in this context
+        #[clap(long, action)]
+        no_remember: bool,
+        /// Only offer contexts with this tag, as key=value or key, can be repeated
+        #[clap(long = "tag", value_parser)]
+        tags: Vec<String>,
+        /// Check that the API server accepts the credentials before switching
+        #[clap(long, action)]
+        verify: bool,
+        #[clap(subcommand)]
+        action: Option<ContextAction>,
+    },
+    /// Switch to a cluster, then to one of its contexts (session-specific)
+    Cluster {
+        #[clap(flatten)]
+        args: ModeArgs,
+        /// Ignore the namespace last used in the chosen context
+        #[clap(long, action)]
+        no_remember: bool,
+        /// Only offer contexts with this tag, as key=value or key, can be repeated
+        #[clap(long = "tag", value_parser)]
+        tags: Vec<String>,
+        /// Check that the API server accepts the credentials before switching
+        #[clap(long, action)]
+        verify: bool,
+    },
+    /// Switch the user of the current context (session-specific)
+    User {
+        #[clap(flatten)]
+        args: ModeArgs,
+        /// Impersonate this user
+        #[clap(long = "as", value_parser)]
+        as_user: Option<String>,
+        /// Impersonate this group, can be repeated
+        #[clap(long = "as-group", value_parser)]
+        as_groups: Vec<String>,
     },
     /// Switch to a namespace (session-specific)
     Namespace {
         #[clap(flatten)]
         args: ModeArgs,
+        /// Switch even if the namespace can't be found in the namespace list
+        #[clap(short, long, action)]
+        force: bool,
+        #[clap(flatten)]
+        filter: NamespaceFilter,
     },
     /// Switch to a context (global, modifies kubeconfig)
     DefaultContext {
         #[clap(flatten)]
         args: ModeArgs,
+        /// Only offer contexts with this tag, as key=value or key, can be repeated
+        #[clap(long = "tag", value_parser)]
+        tags: Vec<String>,
     },
     /// Switch to a namespace (global, modifies kubeconfig)
     DefaultNamespace {
         #[clap(flatten)]
         args: ModeArgs,
+        /// Switch even if the namespace can't be found in the namespace list
+        #[clap(short, long, action)]
+        force: bool,
+        #[clap(flatten)]
+        filter: NamespaceFilter,
     },
     /// Output completions for context
     CompletionContext {
         #[clap(flatten)]
         args: ModeArgs,
+        /// Print one completion per line with a description after a tab
+        #[clap(long, action)]
+        describe: bool,
     },
     /// Output completions for namespace
     CompletionNamespace {
         #[clap(flatten)]
         args: ModeArgs,
+        /// Print one completion per line with a description after a tab
+        #[clap(long, action)]
+        describe: bool,
+    },
+    /// List contexts with their cluster, user, namespace and tags
+    List {
+        /// Only list contexts with this tag, as key=value or key, can be repeated
+        #[clap(long = "tag", value_parser)]
+        tags: Vec<String>,
+        /// Output format
+        #[clap(short, long, value_enum, default_value = "text")]
+        output: list::Format,
+    },
+    /// Show the session and global context and namespace, and where they come from
+    Status,
+    /// Check that the API server of a context accepts its credentials
+    Check {
+        /// Context to check, the current one by default
+        context: Option<String>,
+    },
+    /// Rebuild the session file when the kubeconfig it was built from changed
+    Refresh {
+        /// Rebuild every session file in the cache
+        #[clap(short, long, action)]
+        all: bool,
+    },
+    /// Import contexts, clusters and users from a kubeconfig file
+    Import {
+        /// Kubeconfig file to import, or - to read it from stdin
+        file: String,
+        /// Prefix the names of everything imported
+        #[clap(long, value_parser)]
+        prefix: Option<String>,
+        /// Write without asking for confirmation
+        #[clap(short, long, action)]
+        yes: bool,
+        /// Only show what would be imported
+        #[clap(long, action)]
+        dry_run: bool,
+    },
+    /// Print a self-contained kubeconfig with a single context
+    Export {
+        /// Context to export
+        context: String,
+        /// Namespace to set in the exported context
+        #[clap(short, long, value_parser)]
+        namespace: Option<String>,
+        /// Inline certificates and keys as base64 data
+        #[clap(long, action)]
+        flatten: bool,
+        /// Write to this file instead of stdout
+        #[clap(short, long, value_parser)]
+        output: Option<PathBuf>,
+    },
+    /// Pin directories to a context and namespace with .kubesess files
+    Dir {
+        #[clap(subcommand)]
+        action: DirAction,
+    },
+    /// Print completions for kubesess itself
+    Completions {
+        /// Shell to generate completions for
+        #[clap(value_enum)]
+        shell: clap_complete::Shell,
     },
     /// Initialize shell integration
     Init {
         /// Shell to generate initialization script for
         #[clap(value_enum)]
         shell: init::Shell,
+        #[clap(flatten)]
+        options: init::Options,
+    },
+}
+
+#[derive(Subcommand)]
+enum ContextAction {
+    /// Rename a context in the kubeconfig file defining it
+    Rename {
+        /// Context to rename
+        old: String,
+        /// New name of the context
+        new: String,
+    },
+    /// Delete a context from the kubeconfig file defining it
+    Delete {
+        /// Context to delete
+        name: String,
+        /// Also delete its cluster and user when no context in any file uses them
+        #[clap(long, action)]
+        prune: bool,
+    },
+    /// Copy a context with its cluster and user to another kubeconfig file
+    Copy {
+        /// Context to copy
+        context: String,
+        /// Kubeconfig file to copy to, created if missing
+        target: PathBuf,
+        /// Name of the copy, the same name by default
+        #[clap(long, value_parser)]
+        name: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum DirAction {
+    /// Pin the current directory to a context, writing a trusted .kubesess file
+    Pin {
+        /// Context to pin
+        context: String,
+        /// Namespace to pin
+        #[clap(short, long, value_parser)]
+        namespace: Option<String>,
     },
+    /// Trust a .kubesess file, the nearest one by default
+    Trust {
+        /// .kubesess file to trust
+        file: Option<PathBuf>,
+    },
+    /// Stop trusting a .kubesess file, the nearest one by default
+    Untrust {
+        /// .kubesess file to stop trusting
+        file: Option<PathBuf>,
+    },
+    /// Print the session for the nearest trusted .kubesess file, used by the shell hook
+    #[clap(hide = true)]
+    Hook,
 }
 
 fn main() -> Result<(), io::Error> {
     let cli = Cli::parse();
 
     let result = match cli.command {
-        Command::Context { args } => modes::context(args),
-        Command::Namespace { args } => modes::namespace(args),
-        Command::DefaultContext { args } => modes::default_context(args),
-        Command::DefaultNamespace { args } => modes::default_namespace(args),
-        Command::CompletionContext { args } => {
-            modes::completion_context(args);
+        Command::Context {
+            action: Some(action),
+            ..
+        } => match action {
+            ContextAction::Rename { old, new } => edit::rename(&old, &new),
+            ContextAction::Delete { name, prune } => edit::delete(&name, prune),
+            ContextAction::Copy {
+                context,
+                target,
+                name,
+            } => edit::copy(&context, &target, name.as_deref()),
+        }
+        .map_err(error::Error::EditContext),
+        Command::Context {
+            args,
+            no_remember,
+            tags,
+            verify,
+            ..
+        } => modes::context(args, no_remember, &tags, verify),
+        Command::Cluster {
+            args,
+            no_remember,
+            tags,
+            verify,
+        } => modes::cluster(args, no_remember, &tags, verify),
+        Command::User {
+            args,
+            as_user,
+            as_groups,
+        } => modes::user(args, as_user, as_groups),
+        Command::Namespace {
+            args,
+            force,
+            filter,
+        } => modes::namespace(args, force, filter),
+        Command::DefaultContext { args, tags } => modes::default_context(args, &tags),
+        Command::DefaultNamespace {
+            args,
+            force,
+            filter,
+        } => modes::default_namespace(args, force, filter),
+        Command::CompletionContext { args, describe } => {
+            modes::completion_context(args, describe);
+            Ok(())
+        }
+        Command::CompletionNamespace { args, describe } => {
+            modes::completion_namespace(args, describe);
+            Ok(())
+        }
+        Command::List { tags, output } => {
+            list::list(&tags, output);
+            Ok(())
+        }
+        Command::Status => {
+            status::status();
+            Ok(())
+        }
+        Command::Check { context } => check::check(context.as_deref()),
+        Command::Refresh { all } => {
+            modes::refresh(all);
             Ok(())
         }
-        Command::CompletionNamespace { args } => {
-            modes::completion_namespace(args);
+        Command::Import {
+            file,
+            prefix,
+            yes,
+            dry_run,
+        } => import::import(&file, prefix.as_deref(), yes, dry_run),
+        Command::Export {
+            context,
+            namespace,
+            flatten,
+            output,
+        } => export::export(&context, namespace.as_deref(), flatten, output.as_deref())
+            .map_err(error::Error::Export),
+        Command::Dir { action } => match action {
+            DirAction::Pin { context, namespace } => pin::pin(&context, namespace.as_deref()),
+            DirAction::Trust { file } => pin::trust(file.as_deref()).map_err(error::Error::Pin),
+            DirAction::Untrust { file } => pin::untrust(file.as_deref()).map_err(error::Error::Pin),
+            DirAction::Hook => pin::hook(),
+        },
+        Command::Completions { shell } => {
+            completions::print_completions(shell);
             Ok(())
         }
-        Command::Init { shell } => {
-            init::print_init_script(shell);
+        Command::Init { shell, options } => {
+            init::print_init_script(shell, &options);
             Ok(())
         }
     };
@@ -0,0 +1,109 @@
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+
+/// How a value given on the command line matched the available options
+pub enum Resolution {
+    /// The value names exactly one option, or matches only one of them
+    Unique(String),
+    /// The value matches several options, best match first
+    Ambiguous(Vec<String>),
+}
+
+/// Resolves `query` against `candidates`.
+///
+/// An exact name always wins, otherwise `query` is matched as in [`filter`].
+/// Returns `None` when nothing matches.
+pub fn resolve(query: &str, candidates: &[String]) -> Option<Resolution> {
+    if candidates.iter().any(|candidate| candidate == query) {
+        return Some(Resolution::Unique(query.to_owned()));
+    }
+
+    let mut matches = filter(query, candidates);
+    match matches.len() {
+        0 => None,
+        1 => Some(Resolution::Unique(matches.remove(0))),
+        _ => Some(Resolution::Ambiguous(matches)),
+    }
+}
+
+/// Returns the candidates containing `query`, or when there are none the ones
+/// fuzzy matching it, best match first
+pub fn filter(query: &str, candidates: &[String]) -> Vec<String> {
+    let substring_matches: Vec<String> = candidates
+        .iter()
+        .filter(|candidate| candidate.contains(query))
+        .cloned()
+        .collect();
+    if !substring_matches.is_empty() || query.is_empty() {
+        return substring_matches;
+    }
+
+    let matcher = SkimMatcherV2::default();
+    let mut scored: Vec<(i64, &String)> = candidates
+        .iter()
+        .filter_map(|candidate| {
+            matcher
+                .fuzzy_match(candidate, query)
+                .map(|score| (score, candidate))
+        })
+        .collect();
+
+    scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+    scored
+        .into_iter()
+        .map(|(_, candidate)| candidate.clone())
+        .collect()
+}
+
+/// Returns the candidates closest to `target`, best match first.
+///
+/// A candidate is considered close when one contains the other or when the
+/// edit distance is small relative to the length of `target`.
+pub fn closest(target: &str, candidates: &[String]) -> Vec<String> {
+    let max_distance = (target.chars().count() / 3).max(2);
+
+    let mut scored: Vec<(usize, &String)> = candidates
+        .iter()
+        .filter_map(|candidate| {
+            let distance = levenshtein(target, candidate);
+            let related = candidate.contains(target) || target.contains(candidate.as_str());
+            (distance <= max_distance || related).then_some((distance, candidate))
+        })
+        .collect();
+
+    scored.sort();
+    scored
+        .into_iter()
+        .take(3)
+        .map(|(_, candidate)| candidate.clone())
+        .collect()
+}
+
+/// Whether `name` matches `pattern`, where `*` matches any run of characters
+pub fn glob(pattern: &str, name: &str) -> bool {
+    match pattern.split_once('*') {
+        None => pattern == name,
+        Some((prefix, rest)) => name.strip_prefix(prefix).is_some_and(|name| {
+            name.char_indices()
+                .map(|(i, _)| i)
+                .chain([name.len()])
+                .any(|i| glob(rest, &name[i..]))
+        }),
+    }
+}
+
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+
+    previous[b.len()]
+}
@@ -1,6 +1,171 @@
-use crate::{commands, config, error::Error, ModeArgs, DEST, KUBECONFIG};
+use crate::{
+    check, commands,
+    config::{self, ContextOption, KubeConfigs},
+    error::{Error, SetContextError, SetNamespaceError, SetUserError},
+    matching::{self, Resolution},
+    namespaces,
+    state::State,
+    ModeArgs, NamespaceFilter, DEST, KUBECONFIG, KUBESESSCONFIG, SETTINGS,
+};
+use kube::config::Kubeconfig;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// Turns a resolved `-v` value into the option to use, prompting with `query`
+/// when it matched several options
+fn pick(resolution: Resolution, query: &str, prompt: &'static str) -> Result<String, Error> {
+    match resolution {
+        Resolution::Unique(value) => Ok(value),
+        Resolution::Ambiguous(matches) => commands::selectable_list(matches, Some(query), prompt),
+    }
+}
+
+/// The context as shown in the picker, followed by its tags
+fn display(option: &ContextOption) -> String {
+    let tags: Vec<String> = SETTINGS
+        .tags(&option.name)
+        .iter()
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect();
+    if tags.is_empty() {
+        option.label.clone()
+    } else {
+        format!("{}  [{}]", option.label, tags.join(", "))
+    }
+}
+
+/// Prompts for the context, or resolves `value` to it, among the contexts with
+/// all of `tags`. Contexts with the same name in several files are offered once
+/// per file; a name given with `value` picks the first file defining it, like
+/// kubectl does, and a full label such as `name (file)` picks another one.
+fn choose_context(
+    configs: &KubeConfigs,
+    value: Option<String>,
+    tags: &[String],
+) -> Result<ContextOption, Error> {
+    let mut options = configs.context_options();
+    options.retain(|option| SETTINGS.has_tags(&option.name, tags));
+    let mut names: Vec<String> = Vec::new();
+    for option in &options {
+        if !names.contains(&option.name) {
+            names.push(option.name.clone());
+        }
+    }
+
+    let query = value.as_deref().map(str::trim);
+    let not_found = |ctx: &str| {
+        Error::SetContext(SetContextError::KubeContextNotFound {
+            ctx: ctx.to_owned(),
+        })
+    };
+    let candidates: Vec<&ContextOption> = match query {
+        None => options.iter().collect(),
+        Some(query) => match options.iter().find(|option| option.label == query) {
+            Some(option) => vec![option],
+            None => match matching::resolve(query, &names) {
+                Some(Resolution::Unique(name)) => options
+                    .iter()
+                    .find(|option| option.name == name)
+                    .into_iter()
+                    .collect(),
+                Some(Resolution::Ambiguous(matches)) => matches
+                    .iter()
+                    .flat_map(|name| options.iter().filter(move |option| option.name == *name))
+                    .collect(),
+                None => return Err(not_found(query)),
+            },
+        },
+    };
+
+    let label = match candidates.as_slice() {
+        [option] if query.is_some() => option.label.clone(),
+        _ => {
+            let displays: Vec<String> = candidates.iter().map(|option| display(option)).collect();
+            let selection = commands::selectable_list(displays, query, "context")?;
+            options
+                .iter()
+                .find(|option| display(option) == selection)
+                .map(|option| option.label.clone())
+                .ok_or_else(|| not_found(&selection))?
+        }
+    };
+    let index = options
+        .iter()
+        .position(|option| option.label == label)
+        .ok_or_else(|| not_found(&label))?;
+    let option = options.swap_remove(index);
+
+    warn_duplicates(configs, &option);
+    Ok(option)
+}
+
+/// Warns when the name of `option`, its cluster or its user is defined in
+/// several files, naming the file the definition is taken from
+fn warn_duplicates(configs: &KubeConfigs, option: &ContextOption) {
+    let duplicates = [
+        ("context", &option.name, configs.duplicate_contexts()),
+        ("cluster", &option.cluster, configs.duplicate_clusters()),
+        ("user", &option.user, configs.duplicate_users()),
+    ];
+    for (kind, name, duplicates) in duplicates {
+        let Some(paths) = duplicates.get(name) else {
+            continue;
+        };
+        // The context's own file wins, then the first file defining the name
+        let used = paths
+            .iter()
+            .find(|path| **path == option.path)
+            .unwrap_or(&paths[0]);
+        let others: Vec<String> = paths
+            .iter()
+            .filter(|path| *path != used)
+            .map(|path| path.display().to_string())
+            .collect();
+        eprintln!(
+            "warning: {} {} is also defined in {}, using the one in {}",
+            kind,
+            name,
+            others.join(", "),
+            used.display()
+        );
+    }
+}
+
+/// Prompts for the namespace of `ctx`, or resolves `value` to it, among the
+/// namespaces selected by `filter`
+fn choose_namespace(
+    ctx: &str,
+    value: Option<String>,
+    force: bool,
+    config: &Kubeconfig,
+    filter: &NamespaceFilter,
+) -> Result<String, Error> {
+    let selector = filter.selector.as_deref();
+    match value {
+        None => {
+            let entries = namespaces::list(ctx, config, selector, filter.all);
+            if let (Some(selector), true) = (selector, entries.is_empty()) {
+                return Err(Error::SetNamespace(SetNamespaceError::NoneSelected {
+                    selector: selector.to_owned(),
+                }));
+            }
+            let displays: Vec<String> = entries.iter().map(|entry| entry.display()).collect();
+            let selection = commands::selectable_list(displays, None, "namespace")?;
+            Ok(entries
+                .into_iter()
+                .find(|entry| entry.display() == selection)
+                .map_or(selection, |entry| entry.name))
+        }
+        Some(x) if force => Ok(x.trim().to_owned()),
+        Some(x) => {
+            let resolution = namespaces::resolve(ctx, x.trim(), config, selector)
+                .map_err(Error::SetNamespace)?;
+            pick(resolution, x.trim(), "namespace")
+        }
+    }
+}
 
-pub fn default_context(args: ModeArgs) -> Result<(), Error> {
+pub fn default_context(args: ModeArgs, tags: &[String]) -> Result<(), Error> {
     let config = config::get(None);
 
     if args.current {
@@ -15,46 +180,27 @@ pub fn default_context(args: ModeArgs) -> Result<(), Error> {
         return Ok(());
     }
 
-    let ctx = match args.value {
-        None => {
-            let options: Vec<String> = config
-                .config
-                .contexts
-                .iter()
-                .map(|context| context.name.to_string())
-                .collect();
-
-            commands::selectable_list(options).ok_or(Error::NoItemSelected { prompt: "context" })?
-        }
-        Some(x) => x.trim().to_string(),
-    };
-
-    if let Some(target) = config
-        .configs
-        .iter()
-        .find(|(kubeconfig, _)| {
-            kubeconfig
-                .contexts
-                .iter()
-                .any(|context| context.name == ctx)
-        })
-        .map(|(_, path)| path.clone())
-    {
-        commands::set_default_context(&ctx, &target);
-        // TODO: We should move the target to the front of the line instead of inserting a
-        // duplicate
-        println!("{}:{}", target.to_string_lossy(), KUBECONFIG.as_str());
-    }
+    let option = choose_context(&config, args.value, tags)?;
 
+    commands::set_default_context(&option.name, &option.path);
+    // TODO: We should move the target to the front of the line instead of inserting a
+    // duplicate
+    println!("{}:{}", option.path.to_string_lossy(), KUBECONFIG.as_str());
     Ok(())
 }
 
-pub fn context(args: ModeArgs) -> Result<(), Error> {
+pub fn context(
+    args: ModeArgs,
+    no_remember: bool,
+    tags: &[String],
+    verify: bool,
+) -> Result<(), Error> {
     let current_session = config::get_current_session();
     if args.current {
         println!(
             "{}",
             current_session
+                .config
                 .current_context
                 .as_deref()
                 .unwrap_or("No current context set")
@@ -62,38 +208,194 @@ pub fn context(args: ModeArgs) -> Result<(), Error> {
         return Ok(());
     }
 
-    let config = config::get(None);
-    let ctx = match args.value {
-        None => {
-            let options: Vec<String> = config
-                .config
-                .contexts
-                .iter()
-                .map(|context| context.name.to_string())
-                .collect();
+    let option = choose_context(&config::get(None), args.value, tags)?;
+    switch_context(current_session, option, no_remember, verify)
+}
+
+/// Writes the session file for `option`, with the namespace last used there
+/// unless `no_remember` is set. With `verify`, the shell only switches to it
+/// when the API server accepts its credentials.
+fn switch_context(
+    current_session: KubeConfigs,
+    option: ContextOption,
+    no_remember: bool,
+    verify: bool,
+) -> Result<(), Error> {
+    let ctx = option.name;
+    // The chosen file goes first so that build picks its context, cluster and user
+    let current_session = current_session.prefer(&option.path);
+
+    let remembered_namespace = if SETTINGS.remember_namespace && !no_remember {
+        State::load().namespaces.remove(&ctx)
+    } else {
+        None
+    };
+
+    let filename = commands::set_context(
+        &ctx,
+        remembered_namespace.as_deref(),
+        &DEST,
+        &current_session,
+    )
+    .map_err(Error::SetContext)?;
+    let filename = str::replace(&filename, ":", "_");
+    if verify {
+        check::verify(&ctx, &Path::new(DEST.as_str()).join(&filename))?;
+    }
 
-            commands::selectable_list(options).ok_or(Error::NoItemSelected { prompt: "context" })?
+    println!("{}/{}:{}", &DEST.as_str(), filename, *KUBECONFIG);
+
+    Ok(())
+}
+
+pub fn cluster(
+    args: ModeArgs,
+    no_remember: bool,
+    tags: &[String],
+    verify: bool,
+) -> Result<(), Error> {
+    let current_session = config::get_current_session();
+    if args.current {
+        let config = &current_session.config;
+        let cluster = config
+            .contexts
+            .iter()
+            .find(|context| config.current_context.as_ref() == Some(&context.name))
+            .and_then(|context| context.context.as_ref())
+            .map(|context| context.cluster.as_str());
+        println!("{}", cluster.unwrap_or("No current cluster set"));
+        return Ok(());
+    }
+
+    let mut options = config::get(None).context_options();
+    options.retain(|option| SETTINGS.has_tags(&option.name, tags));
+    let mut clusters: Vec<String> = Vec::new();
+    for option in &options {
+        if !clusters.contains(&option.cluster) {
+            clusters.push(option.cluster.clone());
         }
-        Some(x) => x.trim().to_string(),
+    }
+
+    let cluster = match args.value {
+        None => commands::selectable_list(clusters, None, "cluster")?,
+        Some(x) => match matching::resolve(x.trim(), &clusters) {
+            Some(resolution) => pick(resolution, x.trim(), "cluster")?,
+            None => {
+                return Err(Error::SetContext(SetContextError::ClusterNotFound {
+                    cluster: x.trim().to_owned(),
+                }))
+            }
+        },
     };
 
-    let set_context_result =
-        commands::set_context(&ctx, &DEST, &current_session).map_err(Error::SetContext);
+    // One context for the cluster is used right away, several are offered by user
+    let mut candidates: Vec<ContextOption> = options
+        .into_iter()
+        .filter(|option| option.cluster == cluster)
+        .collect();
+    if candidates.is_empty() {
+        return Err(Error::SetContext(SetContextError::ClusterNotFound {
+            cluster,
+        }));
+    }
 
-    if set_context_result.is_ok() {
-        println!(
-            "{}/{}:{}",
-            &DEST.as_str(),
-            str::replace(&set_context_result.unwrap(), ":", "_"),
-            *KUBECONFIG
-        );
+    let index = if candidates.len() > 1 {
+        let labels: Vec<String> = candidates
+            .iter()
+            .map(|option| format!("{} (user {})", option.label, option.user))
+            .collect();
+        let label = commands::selectable_list(labels.clone(), None, "context")?;
+        labels
+            .iter()
+            .position(|x| *x == label)
+            .ok_or(Error::SetContext(SetContextError::KubeContextNotFound {
+                ctx: label,
+            }))?
+    } else {
+        0
+    };
+
+    let option = candidates.swap_remove(index);
+    warn_duplicates(&current_session, &option);
+    switch_context(current_session, option, no_remember, verify)
+}
+
+/// Switches the user of the current session, keeping its cluster and namespace,
+/// optionally impersonating `as_user` and `as_groups` with it
+pub fn user(args: ModeArgs, as_user: Option<String>, as_groups: Vec<String>) -> Result<(), Error> {
+    let current_session = if args.current {
+        config::get_current_session()
+    } else {
+        config::get_refreshed_session()
+    };
+    let config = &current_session.config;
+    let Some(context) = config
+        .contexts
+        .iter()
+        .find(|context| config.current_context.as_ref() == Some(&context.name))
+    else {
+        return Err(Error::SetUser(SetUserError::NoCurrentContext));
+    };
+    let current_user = context
+        .context
+        .as_ref()
+        .map(|c| c.user.clone())
+        .unwrap_or_default();
+
+    if args.current {
+        println!("{}", current_user);
+        return Ok(());
+    }
+
+    let mut users: Vec<String> = Vec::new();
+    for auth_info in &config::get(None).config.auth_infos {
+        if !users.contains(&auth_info.name) {
+            users.push(auth_info.name.clone());
+        }
+    }
+
+    let user = match args.value {
+        // Impersonating without a user keeps the current one
+        None if as_user.is_some() || !as_groups.is_empty() => current_user,
+        None => commands::selectable_list(users, None, "user")?,
+        Some(x) => match matching::resolve(x.trim(), &users) {
+            Some(resolution) => pick(resolution, x.trim(), "user")?,
+            None => {
+                return Err(Error::SetUser(SetUserError::UserNotFound {
+                    user: x.trim().to_owned(),
+                }))
+            }
+        },
+    };
+
+    let mut selected_context = context.clone();
+    if let Some(ref mut ctx) = selected_context.context {
+        ctx.user = user;
+    }
+    let mut minimal_config = config::build(&selected_context, None, &current_session);
+    for named in minimal_config.auth_infos.iter_mut() {
+        let auth_info = named.auth_info.get_or_insert_with(Default::default);
+        auth_info.impersonate = as_user.clone();
+        auth_info.impersonate_groups = Some(as_groups.clone()).filter(|x| !x.is_empty());
     }
 
+    let filename = config::write_session(&minimal_config, &DEST, &current_session);
+    println!(
+        "{}/{}:{}",
+        &DEST.as_str(),
+        str::replace(&filename, ":", "_"),
+        *KUBECONFIG
+    );
     Ok(())
 }
 
-pub fn namespace(args: ModeArgs) -> Result<(), Error> {
-    let config = config::get_current_session();
+pub fn namespace(args: ModeArgs, force: bool, filter: NamespaceFilter) -> Result<(), Error> {
+    let current_session = if args.current {
+        config::get_current_session()
+    } else {
+        config::get_refreshed_session()
+    };
+    let config = &current_session.config;
     let current_ctx = &config
         .current_context
         .as_deref()
@@ -119,17 +421,10 @@ pub fn namespace(args: ModeArgs) -> Result<(), Error> {
         return Ok(());
     }
 
-    let ns = match args.value {
-        None => {
-            let namespaces: Vec<String> = commands::get_namespaces();
-            commands::selectable_list(namespaces).ok_or(Error::NoItemSelected {
-                prompt: "namespace",
-            })?
-        }
-        Some(x) => x.trim().to_string(),
-    };
+    let ns = choose_namespace(current_ctx, args.value, force, config, &filter)?;
 
-    let result = commands::set_namespace(current_ctx, &ns, &DEST, &config);
+    let result = commands::set_namespace(current_ctx, &ns, &DEST, &current_session);
+    State::record_namespace(current_ctx, &ns, SETTINGS.remember_namespace);
 
     println!(
         "{}/{}:{}",
@@ -140,18 +435,24 @@ pub fn namespace(args: ModeArgs) -> Result<(), Error> {
     Ok(())
 }
 
-pub fn default_namespace(args: ModeArgs) -> Result<(), Error> {
+pub fn default_namespace(
+    args: ModeArgs,
+    force: bool,
+    filter: NamespaceFilter,
+) -> Result<(), Error> {
     let current_session = config::get_current_session();
     let config = config::get(None);
     let ctx = &current_session
+        .config
         .current_context
         .as_deref()
         .unwrap_or("No current context set");
 
     if args.current {
-        if let Some(ctx) = current_session.contexts.iter().find(|x| {
+        if let Some(ctx) = current_session.config.contexts.iter().find(|x| {
             x.name
                 == current_session
+                    .config
                     .current_context
                     .as_deref()
                     .unwrap_or("No current context set")
@@ -169,15 +470,7 @@ pub fn default_namespace(args: ModeArgs) -> Result<(), Error> {
         return Ok(());
     }
 
-    let ns = match args.value {
-        None => {
-            let namespaces: Vec<String> = commands::get_namespaces();
-            commands::selectable_list(namespaces).ok_or(Error::NoItemSelected {
-                prompt: "namespace",
-            })?
-        }
-        Some(x) => x.trim().to_string(),
-    };
+    let ns = choose_namespace(ctx, args.value, force, &current_session.config, &filter)?;
 
     if let Some(target) = config
         .configs
@@ -194,6 +487,7 @@ pub fn default_namespace(args: ModeArgs) -> Result<(), Error> {
     }
 
     let result = commands::set_namespace(ctx, &ns, &DEST, &current_session);
+    State::record_namespace(ctx, &ns, SETTINGS.remember_namespace);
     println!(
         "{}/{}:{}",
         &DEST.as_str(),
@@ -204,33 +498,98 @@ pub fn default_namespace(args: ModeArgs) -> Result<(), Error> {
     Ok(())
 }
 
-pub fn completion_context(args: ModeArgs) {
+/// Prints the completions for `-v`, space separated, or with `describe` one per
+/// line as the name and a description separated by a tab
+fn print_completions(options: Vec<String>, describe: bool, description: impl Fn(&str) -> String) {
+    if describe {
+        for option in options {
+            let description = description(&option);
+            println!("{}\t{}", option, description);
+        }
+    } else {
+        println!("{}", options.join(" "));
+    }
+}
+
+pub fn completion_context(args: ModeArgs, describe: bool) {
     let config = config::get(None);
 
     let search_value = args.value.as_deref().unwrap_or("");
 
-    let options: Vec<String> = config
+    let mut contexts: Vec<String> = config
         .config
         .contexts
         .iter()
-        .filter(|context| context.name.starts_with(search_value))
         .map(|context| context.name.clone())
         .collect();
-
-    println!("{}", options.join(" "));
+    let mut seen = HashSet::new();
+    contexts.retain(|context| seen.insert(context.clone()));
+    let options = matching::filter(search_value, &contexts);
+
+    print_completions(options, describe, |name| {
+        let context = config
+            .config
+            .contexts
+            .iter()
+            .find(|context| context.name == name)
+            .and_then(|context| context.context.as_ref());
+        let cluster = context.map(|c| c.cluster.as_str()).unwrap_or_default();
+        match context.and_then(|c| c.namespace.as_deref()) {
+            Some(ns) => format!("cluster {}, namespace {}", cluster, ns),
+            None => format!("cluster {}", cluster),
+        }
+    });
 }
 
-pub fn completion_namespace(args: ModeArgs) {
-    let namespaces = commands::get_namespaces();
-    let mut options = Vec::new();
-
+pub fn completion_namespace(args: ModeArgs, describe: bool) {
+    let config = config::get_current_session().config;
+    let entries = namespaces::list(
+        config.current_context.as_deref().unwrap_or_default(),
+        &config,
+        None,
+        false,
+    );
+    let namespaces: Vec<String> = entries.iter().map(|entry| entry.name.clone()).collect();
     let search_value = args.value.as_deref().unwrap_or("");
+    let options = matching::filter(search_value, &namespaces);
+
+    print_completions(options, describe, |name| {
+        entries
+            .iter()
+            .find(|entry| entry.name == name)
+            .and_then(|entry| entry.phase.clone())
+            .unwrap_or_else(|| "namespace".to_owned())
+    });
+}
 
-    for ns in &namespaces {
-        if ns.starts_with(search_value) {
-            options.push(ns.to_string());
+/// Rebuilds the session file of this shell, or every one in the cache with
+/// `all`, when the kubeconfig files they were built from changed
+pub fn refresh(all: bool) {
+    let configs = config::get(None);
+    let paths: Vec<PathBuf> = if all {
+        config::session_files(Path::new(DEST.as_str()))
+    } else if KUBESESSCONFIG.is_empty() {
+        Vec::new()
+    } else {
+        vec![PathBuf::from(KUBESESSCONFIG.as_str())]
+    };
+
+    let mut stale = false;
+    for path in paths {
+        match config::refresh(&path, &configs) {
+            Some(config::Refresh::Rebuilt(reason)) => {
+                println!("Refreshed {}, {}", path.display(), reason)
+            }
+            Some(config::Refresh::Failed(reason)) => println!(
+                "Could not refresh {}, {}, switch again with kc",
+                path.display(),
+                reason
+            ),
+            None => continue,
         }
+        stale = true;
+    }
+    if !stale {
+        println!("Nothing to refresh");
     }
-
-    println!("{}", options.join(" "));
 }
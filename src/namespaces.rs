@@ -0,0 +1,190 @@
+use crate::{
+    commands,
+    error::{ListNamespacesError, SetNamespaceError},
+    matching::{self, Resolution},
+    state::State,
+    SETTINGS,
+};
+use k8s_openapi::api::core::v1::Namespace;
+use kube::config::Kubeconfig;
+
+/// A namespace to pick from, with its phase when it was listed from the API
+pub struct Entry {
+    pub name: String,
+    pub phase: Option<String>,
+}
+
+impl Entry {
+    fn named(name: String) -> Entry {
+        Entry { name, phase: None }
+    }
+
+    /// The namespace as shown in the picker, marked when it isn't active
+    pub fn display(&self) -> String {
+        match self.phase.as_deref() {
+            Some(phase) if phase != "Active" => format!("{}  ({})", self.name, phase),
+            _ => self.name.clone(),
+        }
+    }
+}
+
+impl From<Namespace> for Entry {
+    fn from(namespace: Namespace) -> Entry {
+        Entry {
+            name: namespace.metadata.name.unwrap_or_default(),
+            phase: namespace.status.and_then(|status| status.phase),
+        }
+    }
+}
+
+/// Lists the namespaces of `ctx` matching the label `selector`, without the
+/// hidden ones unless `all` is set.
+///
+/// The live API is asked first, then the list cached from the last successful
+/// listing. When neither is available, e.g. because the user isn't allowed to
+/// list namespaces cluster-wide, the fallback sources are used instead. Those
+/// have no labels, so they aren't filtered by `selector`. A listing that
+/// succeeded but matched nothing stays empty.
+pub fn list(ctx: &str, config: &Kubeconfig, selector: Option<&str>, all: bool) -> Vec<Entry> {
+    let fallback = || {
+        fallback(ctx, config)
+            .into_iter()
+            .map(Entry::named)
+            .collect()
+    };
+    let mut entries: Vec<Entry> = match fetch(ctx, selector) {
+        Ok(namespaces) => namespaces.into_iter().map(Entry::from).collect(),
+        Err(ListNamespacesError::Forbidden) => fallback(),
+        Err(ListNamespacesError::Unavailable(_)) => {
+            match State::load().namespace_cache.remove(ctx) {
+                Some(names) if !names.is_empty() => names.into_iter().map(Entry::named).collect(),
+                _ => fallback(),
+            }
+        }
+    };
+
+    if !all {
+        entries.retain(|entry| !SETTINGS.is_hidden(&entry.name));
+    }
+    entries
+}
+
+/// Lists the namespaces from the API, caching the names of complete listings
+fn fetch(ctx: &str, selector: Option<&str>) -> Result<Vec<Namespace>, ListNamespacesError> {
+    let namespaces = commands::get_namespaces(selector)?;
+    if selector.is_none() {
+        let names: Vec<String> = namespaces
+            .iter()
+            .filter_map(|namespace| namespace.metadata.name.clone())
+            .collect();
+        State::cache_namespaces(ctx, &names);
+    }
+    Ok(namespaces)
+}
+
+/// Namespaces known without listing them from the API, in order: the namespaces
+/// of every context pointing at the same cluster as `ctx`, the static list for
+/// `ctx` in the kubesess config and the namespaces used before in `ctx`.
+fn fallback(ctx: &str, config: &Kubeconfig) -> Vec<String> {
+    let mut namespaces: Vec<String> = Vec::new();
+
+    let from_kubeconfig =
+        same_cluster_contexts(ctx, config).filter_map(|context| context.namespace.clone());
+    let from_settings = SETTINGS.namespaces.get(ctx).cloned().unwrap_or_default();
+    let from_history = State::load()
+        .namespace_history
+        .remove(ctx)
+        .unwrap_or_default();
+
+    for ns in from_kubeconfig.chain(from_settings).chain(from_history) {
+        if !namespaces.contains(&ns) {
+            namespaces.push(ns);
+        }
+    }
+
+    namespaces
+}
+
+/// Contexts whose cluster has the same server as the cluster of `ctx`, or the
+/// same name when the server is unknown
+fn same_cluster_contexts<'a>(
+    ctx: &str,
+    config: &'a Kubeconfig,
+) -> impl Iterator<Item = &'a kube::config::Context> {
+    let server = |cluster_name: &str| {
+        config
+            .clusters
+            .iter()
+            .find(|cluster| cluster.name == cluster_name)
+            .and_then(|cluster| cluster.cluster.as_ref())
+            .and_then(|cluster| cluster.server.clone())
+    };
+
+    let target = config
+        .contexts
+        .iter()
+        .find(|context| context.name == ctx)
+        .and_then(|context| context.context.as_ref())
+        .map(|context| (context.cluster.clone(), server(&context.cluster)));
+
+    config
+        .contexts
+        .iter()
+        .filter_map(|context| context.context.as_ref())
+        .filter(move |context| match &target {
+            Some((_, Some(target_server))) => {
+                server(&context.cluster).as_ref() == Some(target_server)
+            }
+            Some((target_cluster, None)) => &context.cluster == target_cluster,
+            None => false,
+        })
+}
+
+/// Resolves `value` against the namespaces of `ctx` matching the label
+/// `selector`, matching it by substring or fuzzy score when it isn't an exact
+/// name.
+///
+/// The cache is consulted first so that switching to a known namespace doesn't
+/// hit the API. When namespaces can't be listed only exact names from the
+/// fallback sources are accepted, and when the API can't be reached at all the
+/// value is taken as is unless a cached list says otherwise.
+pub fn resolve(
+    ctx: &str,
+    value: &str,
+    config: &Kubeconfig,
+    selector: Option<&str>,
+) -> Result<Resolution, SetNamespaceError> {
+    let cached = State::load().namespace_cache.remove(ctx);
+    if selector.is_none()
+        && cached
+            .as_ref()
+            .is_some_and(|namespaces| namespaces.iter().any(|x| x == value))
+    {
+        return Ok(Resolution::Unique(value.to_owned()));
+    }
+
+    let namespaces: Vec<String> = match fetch(ctx, selector) {
+        Ok(namespaces) => namespaces
+            .into_iter()
+            .filter_map(|namespace| namespace.metadata.name)
+            .collect(),
+        Err(ListNamespacesError::Forbidden) => {
+            if fallback(ctx, config).iter().any(|x| x == value) {
+                return Ok(Resolution::Unique(value.to_owned()));
+            }
+            return Err(SetNamespaceError::Forbidden {
+                ctx: ctx.to_owned(),
+                ns: value.to_owned(),
+            });
+        }
+        Err(ListNamespacesError::Unavailable(_)) => match cached {
+            Some(namespaces) => namespaces,
+            None => return Ok(Resolution::Unique(value.to_owned())),
+        },
+    };
+
+    matching::resolve(value, &namespaces).ok_or_else(|| SetNamespaceError::NamespaceNotFound {
+        ns: value.to_owned(),
+        suggestions: matching::closest(value, &namespaces),
+    })
+}
@@ -0,0 +1,180 @@
+use crate::{error::Error, matching, SETTINGS};
+
+use std::{
+    env,
+    io::{self, Cursor, IsTerminal, Read, Write},
+    process::{Command, Stdio},
+};
+extern crate skim;
+use skim::prelude::*;
+
+/// The program used to prompt for a selection
+pub enum Picker {
+    /// The embedded skim finder
+    Skim,
+    /// An external `fzf`, so that the user's fzf configuration applies
+    Fzf,
+    /// A numbered list on stderr, answered on stdin
+    Prompt,
+    /// Any command reading the items on stdin and printing the selected one
+    Command(String),
+}
+
+impl Picker {
+    /// The picker from `KUBESESS_PICKER`, or `picker` in the kubesess config,
+    /// defaulting to skim
+    pub fn configured() -> Picker {
+        env::var("KUBESESS_PICKER")
+            .ok()
+            .filter(|picker| !picker.trim().is_empty())
+            .or_else(|| SETTINGS.picker.clone())
+            .map_or(Picker::Skim, |picker| Picker::from(picker.as_str()))
+    }
+
+    /// Prompts the user to select a `prompt` from `input`, starting with `query`
+    /// as the search.
+    ///
+    /// Every picker gets the items in the same order. skim and fzf list them
+    /// bottom-up, so they are told to show the first item at the top like the
+    /// other pickers do.
+    ///
+    /// The built-in pickers fail right away when stdin isn't a terminal since
+    /// there is nobody to ask, and skim falls back to the numbered menu on
+    /// terminals it can't draw on.
+    pub fn select(
+        &self,
+        input: Vec<String>,
+        query: Option<&str>,
+        prompt: &'static str,
+    ) -> Result<String, Error> {
+        let selected = match self {
+            Picker::Skim | Picker::Prompt if !io::stdin().is_terminal() => {
+                return Err(Error::NotATerminal { prompt });
+            }
+            Picker::Skim if dumb_terminal() => numbered_menu(input, query, prompt),
+            Picker::Skim => skim_select(input, query),
+            Picker::Prompt => numbered_menu(input, query, prompt),
+            Picker::Fzf => {
+                let mut fzf = Command::new("fzf");
+                fzf.arg("--tac")
+                    .arg("--prompt")
+                    .arg(format!("{}> ", prompt));
+                if let Some(query) = query {
+                    fzf.arg("--query").arg(query);
+                }
+                external_select(fzf, "fzf", input)?
+            }
+            Picker::Command(command) => {
+                let mut shell = Command::new("sh");
+                shell
+                    .arg("-c")
+                    .arg(command)
+                    .env("KUBESESS_PROMPT", prompt)
+                    .env("KUBESESS_QUERY", query.unwrap_or_default());
+                external_select(shell, command, input)?
+            }
+        };
+
+        selected.ok_or(Error::NoItemSelected { prompt })
+    }
+}
+
+impl From<&str> for Picker {
+    fn from(picker: &str) -> Self {
+        match picker.trim() {
+            "skim" => Picker::Skim,
+            "fzf" => Picker::Fzf,
+            "prompt" => Picker::Prompt,
+            command => Picker::Command(command.to_string()),
+        }
+    }
+}
+
+fn dumb_terminal() -> bool {
+    env::var("TERM").map_or(true, |term| term.is_empty() || term == "dumb")
+}
+
+/// Returns the selected item or `None` if no item was selected
+fn skim_select(input: Vec<String>, query: Option<&str>) -> Option<String> {
+    let options = SkimOptionsBuilder::default()
+        .multi(false)
+        .tac(true)
+        .query(query)
+        .build()
+        .unwrap();
+    let item_reader = SkimItemReader::default();
+
+    let items = item_reader.of_bufread(Cursor::new(input.join("\n")));
+    Skim::run_with(&options, Some(items))
+        .and_then(|out| match out.final_key {
+            Key::Enter => Some(out.selected_items),
+            _ => None,
+        })
+        .filter(|selected_items| !selected_items.is_empty())
+        .map(|selected_items| selected_items[0].output().to_string())
+}
+
+/// Prints the items numbered on stderr and reads the choice, by number or name,
+/// from stdin. Returns `None` if no item was selected
+fn numbered_menu(input: Vec<String>, query: Option<&str>, prompt: &str) -> Option<String> {
+    let options = match query {
+        Some(query) => matching::filter(query, &input),
+        None => input,
+    };
+
+    let mut stderr = io::stderr();
+    for (i, option) in options.iter().enumerate() {
+        writeln!(stderr, "{:>3}) {}", i + 1, option).ok()?;
+    }
+    write!(stderr, "Select {} [1-{}]: ", prompt, options.len()).ok()?;
+    stderr.flush().ok()?;
+
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer).ok()?;
+    let answer = answer.trim();
+
+    answer
+        .parse::<usize>()
+        .ok()
+        .and_then(|number| number.checked_sub(1))
+        .and_then(|index| options.get(index))
+        .or_else(|| options.iter().find(|option| *option == answer))
+        .cloned()
+}
+
+/// Runs `command` with the items on stdin and returns the first line it prints,
+/// or `None` if it printed nothing, e.g. because it was cancelled
+fn external_select(
+    mut command: Command,
+    name: &str,
+    input: Vec<String>,
+) -> Result<Option<String>, Error> {
+    let picker_error = |err: io::Error| Error::Picker {
+        picker: name.to_string(),
+        reason: err.to_string(),
+    };
+
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(picker_error)?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        // The picker may exit before reading everything, which is fine
+        let _ = stdin.write_all(input.join("\n").as_bytes());
+    }
+
+    let mut output = String::new();
+    if let Some(mut stdout) = child.stdout.take() {
+        stdout.read_to_string(&mut output).map_err(picker_error)?;
+    }
+    child.wait().map_err(picker_error)?;
+
+    Ok(output
+        .lines()
+        .next()
+        .map(str::trim)
+        .filter(|selected| !selected.is_empty())
+        .map(ToOwned::to_owned))
+}
@@ -0,0 +1,195 @@
+use crate::{
+    commands, config,
+    error::{Error, PinError, SetContextError},
+    state::State,
+    DEST, KUBECONFIG,
+};
+
+use serde::{Deserialize, Serialize};
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+/// Name of the file pinning a directory and its subdirectories
+const FILE_NAME: &str = ".kubesess";
+
+/// The context and namespace a `.kubesess` file pins its directory to
+#[derive(Serialize, Deserialize)]
+struct Pin {
+    context: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    namespace: Option<String>,
+}
+
+fn io_error(path: &Path, err: std::io::Error) -> PinError {
+    PinError::Io {
+        file: path.display().to_string(),
+        reason: err.to_string(),
+    }
+}
+
+fn current_dir() -> Result<PathBuf, PinError> {
+    env::current_dir().map_err(|err| io_error(Path::new("."), err))
+}
+
+/// The nearest `.kubesess` file in the current directory or above it
+fn find() -> Result<Option<PathBuf>, PinError> {
+    Ok(current_dir()?
+        .ancestors()
+        .map(|dir| dir.join(FILE_NAME))
+        .find(|path| path.is_file()))
+}
+
+/// `file` when given, the nearest `.kubesess` file otherwise, as an absolute path
+fn resolve(file: Option<&Path>) -> Result<PathBuf, PinError> {
+    let path = match file {
+        Some(file) => file.to_path_buf(),
+        None => find()?.ok_or_else(|| PinError::NoPinFile {
+            dir: current_dir()
+                .map(|dir| dir.display().to_string())
+                .unwrap_or_default(),
+        })?,
+    };
+    fs::canonicalize(&path).map_err(|err| io_error(&path, err))
+}
+
+fn read(path: &Path) -> Result<(String, Pin), PinError> {
+    let content = fs::read_to_string(path).map_err(|err| io_error(path, err))?;
+    let pin = serde_yaml::from_str(&content).map_err(|err| PinError::Invalid {
+        file: path.display().to_string(),
+        reason: err.to_string(),
+    })?;
+    Ok((content, pin))
+}
+
+/// Writes a `.kubesess` file pinning the current directory to `ctx` and trusts it
+pub fn pin(ctx: &str, namespace: Option<&str>) -> Result<(), Error> {
+    if !config::get(None)
+        .config
+        .contexts
+        .iter()
+        .any(|context| context.name == ctx)
+    {
+        return Err(Error::SetContext(SetContextError::KubeContextNotFound {
+            ctx: ctx.to_owned(),
+        }));
+    }
+
+    let path = current_dir().map_err(Error::Pin)?.join(FILE_NAME);
+    let pin = Pin {
+        context: ctx.to_owned(),
+        namespace: namespace.map(ToOwned::to_owned),
+    };
+    let content = serde_yaml::to_string(&pin).unwrap();
+    fs::write(&path, &content).map_err(|err| Error::Pin(io_error(&path, err)))?;
+    let path = fs::canonicalize(&path).unwrap_or(path);
+
+    let mut state = State::load();
+    state
+        .trusted_pins
+        .insert(path.display().to_string(), content);
+    state.save();
+
+    println!("Pinned {} to context {}", path.display(), ctx);
+    Ok(())
+}
+
+/// Trusts the current content of `file`, or of the nearest `.kubesess` file,
+/// so that the shell hook switches to it
+pub fn trust(file: Option<&Path>) -> Result<(), PinError> {
+    let path = resolve(file)?;
+    let (content, pin) = read(&path)?;
+
+    let mut state = State::load();
+    state
+        .trusted_pins
+        .insert(path.display().to_string(), content);
+    state.save();
+
+    println!(
+        "Trusted {}, pinning context {}{}",
+        path.display(),
+        pin.context,
+        pin.namespace
+            .map(|ns| format!(" and namespace {}", ns))
+            .unwrap_or_default()
+    );
+    Ok(())
+}
+
+/// Stops trusting `file`, or the nearest `.kubesess` file
+pub fn untrust(file: Option<&Path>) -> Result<(), PinError> {
+    // A deleted file can still be untrusted by the path it had
+    let path = match file {
+        Some(file) if !file.exists() => std::path::absolute(file).unwrap_or(file.to_path_buf()),
+        _ => resolve(file)?,
+    };
+
+    let mut state = State::load();
+    if state
+        .trusted_pins
+        .remove(&path.display().to_string())
+        .is_some()
+    {
+        state.save();
+        println!("Untrusted {}", path.display());
+    } else {
+        println!("{} was not trusted", path.display());
+    }
+    Ok(())
+}
+
+/// Called by the shell hook when the directory changes. Prints the trusted
+/// `.kubesess` file that applies here and the KUBECONFIG for it on the next
+/// line, or nothing when no file applies.
+pub fn hook() -> Result<(), Error> {
+    let Some(path) = find().map_err(Error::Pin)? else {
+        return Ok(());
+    };
+    let path = fs::canonicalize(&path).unwrap_or(path);
+    let (content, pin) = read(&path).map_err(Error::Pin)?;
+
+    let trusted = State::load()
+        .trusted_pins
+        .get(&path.display().to_string())
+        .cloned();
+    match trusted {
+        Some(trusted) if trusted == content => {}
+        Some(_) => {
+            eprintln!(
+                "kubesess: {} changed since it was trusted, run `kubesess dir trust` to use it",
+                path.display()
+            );
+            return Ok(());
+        }
+        None => {
+            eprintln!(
+                "kubesess: {} is not trusted, run `kubesess dir trust` to use it",
+                path.display()
+            );
+            return Ok(());
+        }
+    }
+
+    let configs = config::get_current_session();
+    let configs = match configs
+        .context_options()
+        .into_iter()
+        .find(|option| option.name == pin.context)
+    {
+        Some(option) => configs.prefer(&option.path),
+        None => configs,
+    };
+    let filename = commands::set_context(&pin.context, pin.namespace.as_deref(), &DEST, &configs)
+        .map_err(Error::SetContext)?;
+
+    println!("{}", path.display());
+    println!(
+        "{}/{}:{}",
+        &DEST.as_str(),
+        str::replace(&filename, ":", "_"),
+        *KUBECONFIG
+    );
+    Ok(())
+}
@@ -0,0 +1,72 @@
+use crate::{matching, KUBESESS_HOME};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// User settings read from `~/.kube/kubesess/config.yaml`
+#[derive(Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct Settings {
+    /// Re-apply the namespace last used in a context when switching back to it
+    pub remember_namespace: bool,
+    /// Namespaces per context to offer when they can't be listed from the API
+    pub namespaces: BTreeMap<String, Vec<String>>,
+    /// Picker to prompt with: `skim`, `fzf`, `prompt` or a command to run
+    pub picker: Option<String>,
+    /// Tags per context, e.g. `env: prod`, to filter contexts with `--tag`
+    pub tags: BTreeMap<String, BTreeMap<String, String>>,
+    /// Patterns of namespaces left out of the picker and completions
+    pub hidden_namespaces: Vec<String>,
+    /// Rebuild the session file when the kubeconfig it was built from changed
+    pub refresh_sessions: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            remember_namespace: true,
+            namespaces: BTreeMap::new(),
+            picker: None,
+            tags: BTreeMap::new(),
+            hidden_namespaces: vec!["kube-*".to_string()],
+            refresh_sessions: true,
+        }
+    }
+}
+
+impl Settings {
+    /// The tags of `ctx`
+    pub fn tags(&self, ctx: &str) -> BTreeMap<String, String> {
+        self.tags.get(ctx).cloned().unwrap_or_default()
+    }
+
+    /// Whether `ctx` has all of `filters`, each either `key=value` or a `key`
+    /// that just has to be present
+    pub fn has_tags(&self, ctx: &str, filters: &[String]) -> bool {
+        let tags = self.tags(ctx);
+        filters.iter().all(|filter| match filter.split_once('=') {
+            Some((key, value)) => tags.get(key).is_some_and(|x| x == value),
+            None => tags.contains_key(filter),
+        })
+    }
+
+    /// Whether `namespace` is left out of the picker and completions
+    pub fn is_hidden(&self, namespace: &str) -> bool {
+        self.hidden_namespaces
+            .iter()
+            .any(|pattern| matching::glob(pattern, namespace))
+    }
+}
+
+pub fn load() -> Settings {
+    let path = Path::new(KUBESESS_HOME.as_str()).join("config.yaml");
+
+    match fs::read_to_string(&path) {
+        Ok(content) => serde_yaml::from_str(&content).unwrap_or_else(|err| {
+            eprintln!("warning: ignoring invalid {}: {}", path.display(), err);
+            Settings::default()
+        }),
+        Err(_) => Settings::default(),
+    }
+}
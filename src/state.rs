@@ -0,0 +1,79 @@
+use crate::KUBESESS_HOME;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+/// State kept between invocations in `~/.kube/kubesess/state.yaml`
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct State {
+    /// Last namespace used per context
+    pub namespaces: BTreeMap<String, String>,
+    /// Namespaces per context from the last successful listing
+    pub namespace_cache: BTreeMap<String, Vec<String>>,
+    /// Namespaces used before per context, most recent first
+    pub namespace_history: BTreeMap<String, Vec<String>>,
+    /// Content of the `.kubesess` files trusted by path
+    pub trusted_pins: BTreeMap<String, String>,
+}
+
+const HISTORY_LIMIT: usize = 20;
+
+fn path() -> PathBuf {
+    PathBuf::from(KUBESESS_HOME.as_str()).join("state.yaml")
+}
+
+impl State {
+    pub fn load() -> State {
+        fs::read_to_string(path())
+            .ok()
+            .and_then(|content| serde_yaml::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) {
+        let path = path();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).expect("Could not create kubesess dir");
+        }
+
+        let content = serde_yaml::to_string(self).unwrap();
+        if let Err(err) = fs::write(&path, content) {
+            eprintln!("warning: could not write {}: {}", path.display(), err);
+        }
+    }
+
+    /// Adds `namespace` to the history of `context` and, if `remember` is set,
+    /// remembers it as the last one used there
+    pub fn record_namespace(context: &str, namespace: &str, remember: bool) {
+        let mut state = State::load();
+        if remember {
+            state
+                .namespaces
+                .insert(context.to_string(), namespace.to_string());
+        }
+
+        let history = state
+            .namespace_history
+            .entry(context.to_string())
+            .or_default();
+        history.retain(|ns| ns != namespace);
+        history.insert(0, namespace.to_string());
+        history.truncate(HISTORY_LIMIT);
+
+        state.save();
+    }
+
+    /// Caches the namespaces listed for `context`
+    pub fn cache_namespaces(context: &str, namespaces: &[String]) {
+        let mut state = State::load();
+        if state.namespace_cache.get(context).map(Vec::as_slice) == Some(namespaces) {
+            return;
+        }
+        state
+            .namespace_cache
+            .insert(context.to_string(), namespaces.to_vec());
+        state.save();
+    }
+}
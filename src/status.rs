@@ -0,0 +1,105 @@
+use crate::{config, KUBECONFIG};
+
+use kube::config::Kubeconfig;
+use std::{env, path::Path};
+
+/// The context and namespace `kubeconfig` selects
+fn selection(kubeconfig: &Kubeconfig) -> Option<(String, String)> {
+    let ctx = kubeconfig.current_context.as_deref()?;
+    let namespace = kubeconfig
+        .contexts
+        .iter()
+        .find(|context| context.name == ctx)
+        .and_then(|context| context.context.as_ref())
+        .and_then(|context| context.namespace.clone())
+        .unwrap_or_else(|| "default".to_owned());
+    Some((ctx.to_owned(), namespace))
+}
+
+/// Prints the session and global context and namespace, the files they come
+/// from and the KUBECONFIG list, warning when the session file is missing or
+/// stale
+pub fn status() {
+    let sources = config::get(None);
+    let kubeconfig = env::var("KUBECONFIG").unwrap_or_default();
+    let paths: Vec<&str> = kubeconfig.split(':').filter(|p| !p.is_empty()).collect();
+    let mut warnings = Vec::new();
+
+    println!("Session");
+    let mut session_selection = None;
+    match paths.iter().find(|path| path.contains("/kubesess/cache")) {
+        None => println!("  none, kubectl uses the global context"),
+        Some(path) if !Path::new(path).exists() => {
+            println!("  file:      {} (missing)", path);
+            warnings.push(format!(
+                "session file {} is missing, switch again with kc",
+                path
+            ));
+        }
+        Some(path) => match Kubeconfig::read_from(path) {
+            Err(err) => warnings.push(format!("could not read session file {}: {}", path, err)),
+            Ok(session) => {
+                let (ctx, namespace) = selection(&session).unwrap_or_default();
+                let source = sources
+                    .context_options()
+                    .into_iter()
+                    .find(|option| option.name == ctx)
+                    .map(|option| option.path.display().to_string())
+                    .unwrap_or_else(|| "none".to_owned());
+                println!("  context:   {}", ctx);
+                println!("  namespace: {}", namespace);
+                println!("  file:      {}", path);
+                println!("  source:    {}", source);
+
+                if let Some(reason) = config::staleness(&session, &sources) {
+                    warnings.push(format!(
+                        "session file is stale, {}, run kubesess refresh to rebuild it",
+                        reason
+                    ));
+                }
+                session_selection = Some((ctx, namespace));
+            }
+        },
+    }
+
+    println!("Global");
+    match selection(&sources.config) {
+        None => println!("  none"),
+        Some((ctx, namespace)) => {
+            let file = sources
+                .configs
+                .iter()
+                .find(|(kubeconfig, _)| kubeconfig.current_context.is_some())
+                .map(|(_, path)| path.display().to_string())
+                .unwrap_or_default();
+            println!("  context:   {}", ctx);
+            println!("  namespace: {}", namespace);
+            println!("  file:      {}", file);
+            if session_selection.is_some_and(|session| session != (ctx, namespace)) {
+                println!("  differs from the session");
+            }
+        }
+    }
+
+    println!("KUBECONFIG");
+    if paths.is_empty() {
+        println!("  not set, kubectl uses ~/.kube/config");
+    }
+    for path in &paths {
+        if Path::new(path).exists() {
+            println!("  {}", path);
+        } else {
+            println!("  {} (missing)", path);
+        }
+    }
+    // Files kubesess reads that kubectl doesn't see in this shell
+    for path in KUBECONFIG.split(':').filter(|p| !p.is_empty()) {
+        if !paths.contains(&path) {
+            println!("  {} (found by kubesess, not in KUBECONFIG)", path);
+        }
+    }
+
+    for warning in warnings {
+        eprintln!("warning: {}", warning);
+    }
+}
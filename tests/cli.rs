@@ -58,7 +58,7 @@ fn reset_environment() {
 fn set_context() -> Result<(), Box<dyn std::error::Error>> {
     let (_temp_dir, _home_dir, kubeconfig) = setup_environment();
 
-    let expected: String = format!("{}", kubeconfig);
+    let expected: String = kubeconfig.to_string();
 
     let mut cmd = Command::cargo_bin("kubesess")?;
     let output = cmd.arg("context").arg("-v").arg("docker-desktop").unwrap();
@@ -75,7 +75,7 @@ fn set_context() -> Result<(), Box<dyn std::error::Error>> {
 fn set_default_context() -> Result<(), Box<dyn std::error::Error>> {
     let (_temp_dir, _home_dir, kubeconfig) = setup_environment();
 
-    let expected: String = format!("{}", kubeconfig);
+    let expected: String = kubeconfig.to_string();
 
     let mut cmd = Command::cargo_bin("kubesess")?;
 
@@ -92,7 +92,7 @@ fn set_default_context() -> Result<(), Box<dyn std::error::Error>> {
 #[serial]
 fn set_namespace() -> Result<(), Box<dyn std::error::Error>> {
     let (_temp_dir, _home_dir, kubeconfig) = setup_environment();
-    let expected: String = format!("{}", kubeconfig);
+    let expected: String = kubeconfig.to_string();
 
     let mut cmd = Command::cargo_bin("kubesess")?;
 
@@ -109,7 +109,7 @@ fn set_namespace() -> Result<(), Box<dyn std::error::Error>> {
 fn set_default_namespace() -> Result<(), Box<dyn std::error::Error>> {
     reset_environment();
     let (_temp_dir, _home_dir, kubeconfig) = setup_environment();
-    let expected: String = format!("{}", kubeconfig);
+    let expected: String = kubeconfig.to_string();
 
     let mut cmd = Command::cargo_bin("kubesess")?;
 
@@ -1195,7 +1195,7 @@ fn multi_kubeconfig_nonexistent_context_error() -> Result<(), Box<dyn std::error
     reset_environment();
     let env = setup_multi_kubeconfig_environment();
 
-    let kubeconfig_value = format!("{}", env.config_path.display());
+    let kubeconfig_value = env.config_path.display().to_string();
     std::env::set_var("KUBECONFIG", &kubeconfig_value);
 
     // Try to switch to non-existent context
@@ -1558,3 +1558,2358 @@ fn multi_kubeconfig_mixed_operations_stress_test() -> Result<(), Box<dyn std::er
     reset_environment();
     Ok(())
 }
+
+// =============================================================================
+// Scenario 19: Namespace memory per context
+// =============================================================================
+
+fn switch_and_export(args: &[&str]) -> Result<String, Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    let output = cmd.args(args).output()?;
+    assert!(
+        output.status.success(),
+        "{:?} failed: {}",
+        args,
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let stdout = String::from_utf8(output.stdout)?.trim().to_owned();
+    std::env::set_var("KUBECONFIG", &stdout);
+    Ok(stdout)
+}
+
+fn current_namespace() -> Result<String, Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    let output = cmd.arg("namespace").arg("-c").output()?;
+    Ok(String::from_utf8(output.stdout)?.trim().to_owned())
+}
+
+#[test]
+#[serial]
+fn namespace_memory_restores_last_namespace() -> Result<(), Box<dyn std::error::Error>> {
+    reset_environment();
+    let env = setup_multi_kubeconfig_environment();
+
+    let kubeconfig_value = format!(
+        "{}:{}",
+        env.config_path.display(),
+        env.work_path.display()
+    );
+    std::env::set_var("KUBECONFIG", &kubeconfig_value);
+
+    switch_and_export(&["context", "-v", "work-prod"])?;
+    switch_and_export(&["namespace", "-v", "payments"])?;
+    switch_and_export(&["context", "-v", "docker-desktop"])?;
+    assert_eq!(current_namespace()?, "default");
+
+    switch_and_export(&["context", "-v", "work-prod"])?;
+    assert_eq!(
+        current_namespace()?,
+        "payments",
+        "Re-entering work-prod should restore its last namespace"
+    );
+
+    switch_and_export(&["context", "-v", "docker-desktop"])?;
+    switch_and_export(&["context", "-v", "work-prod", "--no-remember"])?;
+    assert_eq!(
+        current_namespace()?,
+        "production",
+        "--no-remember should fall back to the kubeconfig namespace"
+    );
+
+    reset_environment();
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn namespace_memory_can_be_disabled_in_settings() -> Result<(), Box<dyn std::error::Error>> {
+    reset_environment();
+    let env = setup_multi_kubeconfig_environment();
+    fs::write(
+        env.kube_dir.join("kubesess").join("config.yaml"),
+        "remember-namespace: false\n",
+    )?;
+
+    let kubeconfig_value = format!(
+        "{}:{}",
+        env.config_path.display(),
+        env.work_path.display()
+    );
+    std::env::set_var("KUBECONFIG", &kubeconfig_value);
+
+    switch_and_export(&["context", "-v", "work-prod"])?;
+    switch_and_export(&["namespace", "-v", "payments"])?;
+    switch_and_export(&["context", "-v", "docker-desktop"])?;
+    switch_and_export(&["context", "-v", "work-prod"])?;
+
+    assert_eq!(current_namespace()?, "production");
+    let state = fs::read_to_string(env.kube_dir.join("kubesess").join("state.yaml"))?;
+    let state: serde_yaml::Value = serde_yaml::from_str(&state)?;
+    assert!(
+        state["namespaces"]["work-prod"].is_null(),
+        "The namespace should not be remembered: {:?}",
+        state
+    );
+
+    reset_environment();
+    Ok(())
+}
+
+// =============================================================================
+// Scenario 20: Namespace validation
+// =============================================================================
+
+/// Puts a `kubectl` stub running `script` first on PATH and returns the new PATH
+#[cfg(unix)]
+fn fake_kubectl(env: &MultiKubeconfigEnv, script: &str) -> String {
+    use std::os::unix::fs::PermissionsExt;
+
+    let bin_dir = env.temp_dir.path().join("bin");
+    fs::create_dir_all(&bin_dir).expect("Failed to create bin directory");
+    let kubectl = bin_dir.join("kubectl");
+    fs::write(&kubectl, format!("#!/bin/sh\n{}\n", script)).expect("Failed to write kubectl");
+    fs::set_permissions(&kubectl, fs::Permissions::from_mode(0o755))
+        .expect("Failed to make kubectl executable");
+
+    format!("{}:{}", bin_dir.display(), env::var("PATH").unwrap_or_default())
+}
+
+/// A kubectl script printing namespaces like `kubectl get namespace -o json`,
+/// each given as name, phase and labels
+#[cfg(unix)]
+fn namespaces_script(namespaces: &[(&str, &str, &str)]) -> String {
+    let items: Vec<String> = namespaces
+        .iter()
+        .map(|(name, phase, labels)| {
+            format!(
+                r#"{{"apiVersion":"v1","kind":"Namespace","metadata":{{"name":"{}","labels":{{{}}}}},"status":{{"phase":"{}"}}}}"#,
+                name, labels, phase
+            )
+        })
+        .collect();
+    format!(
+        "cat <<'EOF'\n{{\"apiVersion\":\"v1\",\"kind\":\"List\",\"items\":[{}]}}\nEOF",
+        items.join(",")
+    )
+}
+
+#[cfg(unix)]
+#[test]
+#[serial]
+fn namespace_validation_suggests_closest_matches() -> Result<(), Box<dyn std::error::Error>> {
+    reset_environment();
+    let env = setup_multi_kubeconfig_environment();
+    std::env::set_var("KUBECONFIG", env.config_path.display().to_string());
+    let path = fake_kubectl(
+        &env,
+        &namespaces_script(&[
+            ("default", "Active", ""),
+            ("payments", "Active", ""),
+            ("payroll", "Active", ""),
+        ]),
+    );
+
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    let output = cmd
+        .env("PATH", &path)
+        .args(["namespace", "-v", "paymnets"])
+        .output()?;
+    let stderr = String::from_utf8(output.stderr)?;
+
+    assert!(!output.status.success(), "Unknown namespace should fail");
+    assert!(
+        stderr.contains("did you mean: payments"),
+        "Should suggest the closest namespace: {}",
+        stderr
+    );
+
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    let output = cmd
+        .env("PATH", &path)
+        .args(["namespace", "-v", "paymnets", "--force"])
+        .output()?;
+    assert!(output.status.success(), "--force should skip validation");
+
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    let output = cmd
+        .env("PATH", &path)
+        .args(["default-namespace", "-v", "payroll"])
+        .output()?;
+    assert!(output.status.success(), "Existing namespace should pass");
+
+    reset_environment();
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+#[serial]
+fn namespace_validation_requires_force_when_forbidden() -> Result<(), Box<dyn std::error::Error>> {
+    reset_environment();
+    let env = setup_multi_kubeconfig_environment();
+    std::env::set_var("KUBECONFIG", env.config_path.display().to_string());
+    let path = fake_kubectl(
+        &env,
+        "echo 'Error from server (Forbidden): namespaces is forbidden' >&2; exit 1",
+    );
+
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    let output = cmd
+        .env("PATH", &path)
+        .args(["namespace", "-v", "payments"])
+        .output()?;
+    let stderr = String::from_utf8(output.stderr)?;
+
+    assert!(!output.status.success(), "Forbidden listing should fail");
+    assert!(stderr.contains("--force"), "Should point at --force: {}", stderr);
+
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    let output = cmd
+        .env("PATH", &path)
+        .args(["namespace", "-v", "payments", "--force"])
+        .output()?;
+    assert!(output.status.success(), "--force should switch anyway");
+
+    reset_environment();
+    Ok(())
+}
+
+// =============================================================================
+// Scenario 21: Namespace fallback sources without list permission
+// =============================================================================
+
+#[cfg(unix)]
+#[test]
+#[serial]
+fn namespace_fallback_sources_when_listing_is_forbidden() -> Result<(), Box<dyn std::error::Error>> {
+    reset_environment();
+    let env = setup_multi_kubeconfig_environment();
+
+    // A second context pointing at the same cluster as docker-desktop
+    let readonly_path = env.kube_dir.join("readonly.yaml");
+    fs::write(
+        &readonly_path,
+        create_kubeconfig_content(
+            "docker-readonly",
+            "docker-desktop",
+            "readonly-user",
+            "monitoring",
+            None,
+        ),
+    )?;
+    fs::write(
+        env.kube_dir.join("kubesess").join("config.yaml"),
+        "namespaces:\n  docker-desktop: [payments]\n",
+    )?;
+
+    let kubeconfig_value = format!(
+        "{}:{}",
+        env.config_path.display(),
+        readonly_path.display()
+    );
+    std::env::set_var("KUBECONFIG", &kubeconfig_value);
+    let path = fake_kubectl(
+        &env,
+        "echo 'Error from server (Forbidden): namespaces is forbidden' >&2; exit 1",
+    );
+
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    let output = cmd
+        .env("PATH", &path)
+        .args(["namespace", "-v", "ingress", "--force"])
+        .output()?;
+    assert!(output.status.success());
+
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    let output = cmd
+        .env("PATH", &path)
+        .arg("completion-namespace")
+        .output()?;
+    let stdout = String::from_utf8(output.stdout)?;
+    let namespaces: Vec<&str> = stdout.split_whitespace().collect();
+    assert_eq!(
+        namespaces,
+        vec!["default", "monitoring", "payments", "ingress"],
+        "Should list kubeconfig, settings and history namespaces in order"
+    );
+
+    // Namespaces from the fallback sources don't need --force
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    let output = cmd
+        .env("PATH", &path)
+        .args(["namespace", "-v", "monitoring"])
+        .output()?;
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    reset_environment();
+    Ok(())
+}
+
+// =============================================================================
+// Scenario 22: Substring and fuzzy matching of -v values
+// =============================================================================
+
+#[test]
+#[serial]
+fn context_value_matches_by_substring_and_fuzzy_score() -> Result<(), Box<dyn std::error::Error>> {
+    reset_environment();
+    let env = setup_multi_kubeconfig_environment();
+
+    let kubeconfig_value = format!(
+        "{}:{}:{}",
+        env.config_path.display(),
+        env.work_path.display(),
+        env.personal_path.display()
+    );
+    std::env::set_var("KUBECONFIG", &kubeconfig_value);
+
+    for (query, expected) in [("work", "work-prod"), ("psdv", "personal-dev")] {
+        let mut cmd = Command::cargo_bin("kubesess")?;
+        let output = cmd.args(["context", "-v", query]).output()?;
+        let stdout = String::from_utf8(output.stdout)?;
+        assert!(
+            stdout.contains(&format!("{}_", expected)),
+            "{} should resolve to {}: {}",
+            query,
+            expected,
+            stdout
+        );
+    }
+
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    let output = cmd.args(["context", "-v", "nothing-like-it"]).output()?;
+    assert!(!output.status.success(), "Unknown context should fail");
+
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    let output = cmd.args(["completion-context", "-v", "prod"]).output()?;
+    let stdout = String::from_utf8(output.stdout)?;
+    assert_eq!(stdout.trim(), "work-prod");
+
+    reset_environment();
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+#[serial]
+fn namespace_value_matches_by_substring() -> Result<(), Box<dyn std::error::Error>> {
+    reset_environment();
+    let env = setup_multi_kubeconfig_environment();
+    std::env::set_var("KUBECONFIG", env.config_path.display().to_string());
+    let path = fake_kubectl(
+        &env,
+        &namespaces_script(&[
+            ("default", "Active", ""),
+            ("payments", "Active", ""),
+            ("payroll", "Active", ""),
+        ]),
+    );
+
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    let output = cmd
+        .env("PATH", &path)
+        .args(["namespace", "-v", "ments"])
+        .output()?;
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(
+        stdout.contains("docker-desktop_payments"),
+        "ments should resolve to payments: {}",
+        stdout
+    );
+
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    let output = cmd
+        .env("PATH", &path)
+        .args(["completion-namespace", "-v", "pay"])
+        .output()?;
+    let stdout = String::from_utf8(output.stdout)?;
+    assert_eq!(stdout.trim(), "payments payroll");
+
+    reset_environment();
+    Ok(())
+}
+
+// =============================================================================
+// Scenario 23: Picker without a terminal
+// =============================================================================
+
+#[test]
+#[serial]
+fn picker_fails_cleanly_without_terminal() -> Result<(), Box<dyn std::error::Error>> {
+    reset_environment();
+    let env = setup_multi_kubeconfig_environment();
+
+    let kubeconfig_value = format!(
+        "{}:{}:{}",
+        env.config_path.display(),
+        env.work_path.display(),
+        env.personal_path.display()
+    );
+    std::env::set_var("KUBECONFIG", &kubeconfig_value);
+
+    // No -v, and an ambiguous -v, both need the picker
+    for args in [vec!["context"], vec!["context", "-v", "-"]] {
+        let mut cmd = Command::cargo_bin("kubesess")?;
+        let output = cmd.args(&args).output()?;
+        let stderr = String::from_utf8(output.stderr)?;
+
+        assert!(!output.status.success(), "{:?} should fail", args);
+        assert!(
+            stderr.contains("stdin is not a terminal"),
+            "{:?} should explain why it can't prompt: {}",
+            args,
+            stderr
+        );
+    }
+
+    reset_environment();
+    Ok(())
+}
+
+// =============================================================================
+// Scenario 24: Picker backends
+// =============================================================================
+
+#[test]
+#[serial]
+fn picker_command_from_env_and_settings() -> Result<(), Box<dyn std::error::Error>> {
+    reset_environment();
+    let env = setup_multi_kubeconfig_environment();
+    fs::write(
+        env.kube_dir.join("kubesess").join("config.yaml"),
+        "picker: grep personal\n",
+    )?;
+
+    let kubeconfig_value = format!(
+        "{}:{}:{}",
+        env.config_path.display(),
+        env.work_path.display(),
+        env.personal_path.display()
+    );
+    std::env::set_var("KUBECONFIG", &kubeconfig_value);
+
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    let output = cmd.arg("context").output()?;
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(
+        stdout.contains("personal-dev_"),
+        "The picker from the config should select personal-dev: {}",
+        stdout
+    );
+
+    // The environment wins over the config, and gets the query passed along
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    let output = cmd
+        .env("KUBESESS_PICKER", r#"grep -e "${KUBESESS_QUERY}ev""#)
+        .args(["context", "--value=-d"])
+        .output()?;
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(stdout.contains("personal-dev_"), "{}", stdout);
+
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    let output = cmd
+        .env("KUBESESS_PICKER", r#"grep -e "^$KUBESESS_QUERY""#)
+        .args(["context", "-v", "-"])
+        .output()?;
+    assert!(
+        !output.status.success(),
+        "A picker printing nothing means nothing was selected"
+    );
+
+    reset_environment();
+    Ok(())
+}
+
+#[test]
+#[serial]
+#[cfg(unix)]
+fn every_picker_gets_the_same_order() -> Result<(), Box<dyn std::error::Error>> {
+    reset_environment();
+    let env = setup_multi_kubeconfig_environment();
+    let kubeconfig_value = format!(
+        "{}:{}:{}",
+        env.config_path.display(),
+        env.work_path.display(),
+        env.personal_path.display()
+    );
+    std::env::set_var("KUBECONFIG", &kubeconfig_value);
+
+    // fzf is faked by a script recording its arguments and items
+    let path = fake_kubectl(&env, "exit 1");
+    let bin_dir = env.temp_dir.path().join("bin");
+    let fzf_items = env.temp_dir.path().join("fzf-items");
+    let fzf_args = env.temp_dir.path().join("fzf-args");
+    fs::write(
+        bin_dir.join("fzf"),
+        format!(
+            "#!/bin/sh\necho \"$@\" > {}\ntee {} | grep personal\n",
+            fzf_args.display(),
+            fzf_items.display()
+        ),
+    )?;
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(bin_dir.join("fzf"), fs::Permissions::from_mode(0o755))?;
+    }
+
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    let output = cmd
+        .env("PATH", &path)
+        .env("KUBESESS_PICKER", "fzf")
+        .arg("context")
+        .output()?;
+    assert!(String::from_utf8(output.stdout)?.contains("personal-dev_"));
+    assert!(fs::read_to_string(&fzf_args)?.contains("--tac"));
+
+    let command_items = env.temp_dir.path().join("command-items");
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    let output = cmd
+        .env("KUBESESS_PICKER", format!("tee {} | grep personal", command_items.display()))
+        .arg("context")
+        .output()?;
+    assert!(String::from_utf8(output.stdout)?.contains("personal-dev_"));
+
+    let items = fs::read_to_string(&command_items)?;
+    assert_eq!(fs::read_to_string(&fzf_items)?, items);
+    assert!(items.find("docker-desktop") < items.find("personal-dev"), "{}", items);
+
+    reset_environment();
+    Ok(())
+}
+
+// =============================================================================
+// Scenario 25: Importing kubeconfig files
+// =============================================================================
+
+#[test]
+#[serial]
+fn import_renames_conflicts_and_is_discovered() -> Result<(), Box<dyn std::error::Error>> {
+    reset_environment();
+    let env = setup_multi_kubeconfig_environment();
+    std::env::set_var("KUBECONFIG", env.config_path.display().to_string());
+
+    // work-prod conflicts with the one in ~/.kube/work.yaml, staging is new
+    let import_path = env.temp_dir.path().join("import.yaml");
+    fs::write(
+        &import_path,
+        r#"apiVersion: v1
+kind: Config
+clusters:
+- cluster:
+    server: https://other.example.com:6443
+  name: work-cluster
+- cluster:
+    server: https://staging.example.com:6443
+  name: staging
+contexts:
+- context:
+    cluster: work-cluster
+    user: work-user
+  name: work-prod
+- context:
+    cluster: staging
+    namespace: apps
+    user: work-user
+  name: staging
+users:
+- name: work-user
+"#,
+    )?;
+
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    let output = cmd
+        .args(["import", import_path.to_str().unwrap(), "--dry-run"])
+        .output()?;
+    let stdout = String::from_utf8(output.stdout)?;
+    println!("Preview: {}", stdout);
+    assert!(output.status.success());
+    assert!(stdout.contains("+ cluster work-cluster-2 (renamed from work-cluster, name taken)"));
+    assert!(stdout.contains("= user work-user (already present)"));
+    assert!(stdout.contains("+ context staging"));
+    // The entries are shown as they will be written
+    assert!(stdout.contains("    name: work-cluster-2\n"), "{}", stdout);
+    assert!(stdout.contains("server: https://other.example.com:6443"), "{}", stdout);
+    assert!(stdout.contains("namespace: apps"), "{}", stdout);
+
+    let store = env.kube_dir.join("kubesess").join("kubeconfig.yaml");
+    assert!(!store.exists(), "--dry-run should not write anything");
+
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    let output = cmd.args(["import", import_path.to_str().unwrap()]).output()?;
+    assert!(
+        !output.status.success(),
+        "Importing without a terminal to confirm on should need --yes"
+    );
+
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    let output = cmd
+        .args(["import", import_path.to_str().unwrap(), "--yes"])
+        .output()?;
+    assert!(output.status.success());
+
+    let content = fs::read_to_string(&store)?;
+    assert!(content.contains("name: work-prod-2"));
+    assert!(content.contains("cluster: work-cluster-2"));
+
+    // The managed kubeconfig is discovered like the files in ~/.kube
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    let output = cmd.args(["context", "-v", "staging"]).output()?;
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(stdout.contains("staging_apps"), "{}", stdout);
+
+    // Importing the same file again changes nothing
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    let output = cmd
+        .args(["import", import_path.to_str().unwrap(), "--yes"])
+        .output()?;
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(stdout.contains("Nothing to import"), "{}", stdout);
+
+    reset_environment();
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn import_keeps_a_corrupt_store() -> Result<(), Box<dyn std::error::Error>> {
+    reset_environment();
+    let env = setup_multi_kubeconfig_environment();
+    std::env::set_var("KUBECONFIG", env.config_path.display().to_string());
+
+    let store = env.kube_dir.join("kubesess").join("kubeconfig.yaml");
+    fs::create_dir_all(store.parent().unwrap())?;
+    fs::write(&store, "contexts: [unclosed\n")?;
+
+    let import_path = env.temp_dir.path().join("new.yaml");
+    fs::write(
+        &import_path,
+        create_kubeconfig_content("new", "new-cluster", "new-user", "default", None),
+    )?;
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    let output = cmd
+        .args(["import", import_path.to_str().unwrap(), "--yes"])
+        .output()?;
+    assert!(!output.status.success(), "A store that can't be read shouldn't be replaced");
+    let stderr = String::from_utf8(output.stderr)?;
+    assert!(stderr.contains(&store.display().to_string()), "{}", stderr);
+    assert_eq!(fs::read_to_string(&store)?, "contexts: [unclosed\n");
+
+    reset_environment();
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn import_from_stdin_with_prefix() -> Result<(), Box<dyn std::error::Error>> {
+    reset_environment();
+    let env = setup_multi_kubeconfig_environment();
+    std::env::set_var("KUBECONFIG", env.config_path.display().to_string());
+
+    let content = create_kubeconfig_content("dev", "dev-cluster", "dev-user", "default", None);
+    assert_cmd::Command::cargo_bin("kubesess")?
+        .args(["import", "-", "--prefix", "acme-", "--yes"])
+        .write_stdin(content)
+        .assert()
+        .success();
+
+    let store = fs::read_to_string(env.kube_dir.join("kubesess").join("kubeconfig.yaml"))?;
+    assert!(store.contains("name: acme-dev"));
+    assert!(store.contains("cluster: acme-dev-cluster"));
+    assert!(store.contains("user: acme-dev-user"));
+
+    reset_environment();
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn import_from_stdin_resolves_relative_paths() -> Result<(), Box<dyn std::error::Error>> {
+    reset_environment();
+    let env = setup_multi_kubeconfig_environment();
+    std::env::set_var("KUBECONFIG", env.config_path.display().to_string());
+
+    let content = "apiVersion: v1
+kind: Config
+clusters:
+- name: piped-cluster
+  cluster:
+    server: https://piped.example.com
+    certificate-authority: certs/ca.crt
+users:
+- name: piped-user
+  user:
+    client-certificate: certs/client.crt
+    client-key: /etc/certs/client.key
+contexts:
+- name: piped
+  context:
+    cluster: piped-cluster
+    user: piped-user
+";
+    assert_cmd::Command::cargo_bin("kubesess")?
+        .current_dir(&env.kube_dir)
+        .args(["import", "-", "--yes"])
+        .write_stdin(content)
+        .assert()
+        .success();
+
+    let store = fs::read_to_string(env.kube_dir.join("kubesess").join("kubeconfig.yaml"))?;
+    let certs = env.kube_dir.join("certs");
+    assert!(store.contains(&format!("certificate-authority: {}", certs.join("ca.crt").display())), "{}", store);
+    assert!(store.contains(&format!("client-certificate: {}", certs.join("client.crt").display())), "{}", store);
+    assert!(store.contains("client-key: /etc/certs/client.key"), "{}", store);
+
+    reset_environment();
+    Ok(())
+}
+
+// =============================================================================
+// Scenario 26: Renaming, deleting and copying contexts
+// =============================================================================
+
+#[test]
+#[serial]
+fn context_rename_updates_owning_file_and_sessions() -> Result<(), Box<dyn std::error::Error>> {
+    reset_environment();
+    let env = setup_multi_kubeconfig_environment();
+    std::env::set_var("KUBECONFIG", env.config_path.display().to_string());
+
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    let output = cmd.args(["context", "-v", "work-prod"]).output()?;
+    assert!(output.status.success());
+    let session = String::from_utf8(output.stdout)?
+        .split(':')
+        .next()
+        .unwrap()
+        .to_string();
+
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    let output = cmd
+        .args(["context", "rename", "work-prod", "personal-dev"])
+        .output()?;
+    assert!(!output.status.success(), "Renaming onto an existing context should fail");
+
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    let output = cmd.args(["context", "rename", "work-prod", "work"]).output()?;
+    assert!(output.status.success());
+
+    let work = fs::read_to_string(&env.work_path)?;
+    assert!(work.contains("name: work\n"));
+    assert!(work.contains("current-context: work\n"));
+    assert!(!work.contains("work-prod"));
+
+    let session = fs::read_to_string(&session)?;
+    assert!(session.contains("current-context: work\n"), "{}", session);
+
+    reset_environment();
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn context_delete_with_prune_removes_orphans() -> Result<(), Box<dyn std::error::Error>> {
+    reset_environment();
+    let env = setup_multi_kubeconfig_environment();
+    std::env::set_var("KUBECONFIG", env.config_path.display().to_string());
+
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    let output = cmd.args(["context", "delete", "personal-dev", "--prune"]).output()?;
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(output.status.success());
+    assert!(stdout.contains("Deleted unused cluster personal-cluster"));
+    assert!(stdout.contains("Deleted unused user personal-user"));
+
+    let personal = fs::read_to_string(&env.personal_path)?;
+    assert!(!personal.contains("personal"), "{}", personal);
+
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    let output = cmd.args(["context", "delete", "personal-dev"]).output()?;
+    assert!(!output.status.success(), "Deleting a missing context should fail");
+
+    reset_environment();
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn context_delete_prunes_only_what_no_file_uses() -> Result<(), Box<dyn std::error::Error>> {
+    reset_environment();
+    let env = setup_multi_kubeconfig_environment();
+    let team = env.kube_dir.join("team.yaml");
+    fs::write(
+        &team,
+        r#"apiVersion: v1
+kind: Config
+clusters:
+- cluster:
+    server: https://a.example.com
+  name: a-cluster
+- cluster:
+    server: https://b.example.com
+  name: b-cluster
+contexts:
+- context:
+    cluster: a-cluster
+    user: a-user
+  name: a
+- context:
+    cluster: b-cluster
+    user: b-user
+  name: b
+users:
+- name: a-user
+- name: b-user
+- name: shared-user
+"#,
+    )?;
+    let other = env.kube_dir.join("other");
+    fs::write(
+        &other,
+        r#"apiVersion: v1
+kind: Config
+contexts:
+- context:
+    cluster: b-cluster
+    user: shared-user
+  name: other
+"#,
+    )?;
+    std::env::set_var(
+        "KUBECONFIG",
+        format!("{}:{}", team.display(), other.display()),
+    );
+
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    let output = cmd.args(["context", "delete", "b", "--prune"]).output()?;
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(stdout.contains("Deleted unused user b-user"), "{}", stdout);
+    assert!(!stdout.contains("cluster"), "b-cluster is used by other: {}", stdout);
+
+    let content = fs::read_to_string(&team)?;
+    assert!(!content.contains("b-user"), "{}", content);
+    assert!(content.contains("name: b-cluster"), "{}", content);
+    assert!(content.contains("name: shared-user"), "{}", content);
+    assert!(content.contains("name: a-user"), "{}", content);
+
+    reset_environment();
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn context_copy_to_new_file() -> Result<(), Box<dyn std::error::Error>> {
+    reset_environment();
+    let env = setup_multi_kubeconfig_environment();
+    std::env::set_var("KUBECONFIG", env.config_path.display().to_string());
+
+    let target = env.temp_dir.path().join("shared.yaml");
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    let output = cmd
+        .args(["context", "copy", "work-prod", target.to_str().unwrap(), "--name", "shared"])
+        .output()?;
+    assert!(output.status.success());
+
+    let content = fs::read_to_string(&target)?;
+    assert!(content.contains("name: shared"));
+    assert!(content.contains("name: work-cluster"));
+    assert!(content.contains("name: work-user"));
+
+    // The same cluster and user are reused for a second copy
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    let output = cmd
+        .args(["context", "copy", "work-prod", target.to_str().unwrap()])
+        .output()?;
+    assert!(output.status.success());
+    let content = fs::read_to_string(&target)?;
+    assert_eq!(content.matches("name: work-cluster").count(), 1);
+
+    // The original is left alone
+    let work = fs::read_to_string(&env.work_path)?;
+    assert!(work.contains("name: work-prod"));
+
+    reset_environment();
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn context_copy_to_empty_or_invalid_file() -> Result<(), Box<dyn std::error::Error>> {
+    reset_environment();
+    let env = setup_multi_kubeconfig_environment();
+    std::env::set_var("KUBECONFIG", env.config_path.display().to_string());
+
+    // An empty file is started like a missing one
+    let target = env.temp_dir.path().join("empty.yaml");
+    fs::write(&target, "")?;
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    let output = cmd
+        .args(["context", "copy", "work-prod", target.to_str().unwrap()])
+        .output()?;
+    assert!(output.status.success(), "{}", String::from_utf8(output.stderr)?);
+    let content = fs::read_to_string(&target)?;
+    assert!(content.contains("kind: Config"), "{}", content);
+    assert!(content.contains("name: work-prod"), "{}", content);
+
+    let target = env.temp_dir.path().join("list.yaml");
+    fs::write(&target, "- not\n- a kubeconfig\n")?;
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    let output = cmd
+        .args(["context", "copy", "work-prod", target.to_str().unwrap()])
+        .output()?;
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8(output.stderr)?;
+    assert!(stderr.contains("is not a kubeconfig"), "{}", stderr);
+    assert_eq!(fs::read_to_string(&target)?, "- not\n- a kubeconfig\n");
+
+    reset_environment();
+    Ok(())
+}
+
+// =============================================================================
+// Scenario 27: Exporting a context
+// =============================================================================
+
+#[test]
+#[serial]
+fn export_context_with_inlined_certificates() -> Result<(), Box<dyn std::error::Error>> {
+    reset_environment();
+    let env = setup_multi_kubeconfig_environment();
+    std::env::set_var("KUBECONFIG", env.config_path.display().to_string());
+
+    fs::write(env.kube_dir.join("ca.crt"), "ca")?;
+    fs::write(env.kube_dir.join("client.key"), "key")?;
+    fs::write(env.kube_dir.join("token"), "s3cr3t\n")?;
+    fs::write(
+        env.kube_dir.join("certs.yaml"),
+        r#"apiVersion: v1
+kind: Config
+clusters:
+- cluster:
+    certificate-authority: ca.crt
+    server: https://certs.example.com:6443
+  name: certs-cluster
+contexts:
+- context:
+    cluster: certs-cluster
+    user: certs-user
+  name: certs
+users:
+- name: certs-user
+  user:
+    client-key: client.key
+    tokenFile: token
+"#,
+    )?;
+
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    let output = cmd.args(["export", "certs", "-n", "apps"]).output()?;
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(output.status.success());
+    assert!(stdout.contains("current-context: certs"));
+    assert!(stdout.contains("namespace: apps"));
+    assert!(stdout.contains("ca.crt"));
+    assert!(!stdout.contains("work-prod"), "Only the exported context should be included");
+
+    let target = env.temp_dir.path().join("exported.yaml");
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    let output = cmd
+        .args(["export", "certs", "--flatten", "-o", target.to_str().unwrap()])
+        .output()?;
+    assert!(output.status.success());
+
+    let content = fs::read_to_string(&target)?;
+    assert!(content.contains("certificate-authority-data: Y2E="), "{}", content);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = fs::metadata(&target)?.permissions().mode();
+        assert_eq!(mode & 0o777, 0o600, "The key and token shouldn't be readable by others");
+    }
+    assert!(content.contains("client-key-data: a2V5"), "{}", content);
+    assert!(content.contains("token: s3cr3t\n"), "{}", content);
+    assert!(!content.contains("ca.crt"));
+    assert!(!content.contains("tokenFile"), "{}", content);
+
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    let output = cmd.args(["export", "missing"]).output()?;
+    assert!(!output.status.success());
+
+    reset_environment();
+    Ok(())
+}
+
+// =============================================================================
+// Scenario 28: File references in session files
+// =============================================================================
+
+#[test]
+#[serial]
+fn session_file_references_are_absolute() -> Result<(), Box<dyn std::error::Error>> {
+    reset_environment();
+    let env = setup_multi_kubeconfig_environment();
+
+    let project = env.temp_dir.path().join("project");
+    fs::create_dir_all(&project)?;
+    fs::write(project.join("ca.crt"), "ca")?;
+    fs::write(
+        project.join("kubeconfig.yaml"),
+        r#"apiVersion: v1
+kind: Config
+clusters:
+- cluster:
+    certificate-authority: ca.crt
+    server: https://project.example.com:6443
+  name: project-cluster
+contexts:
+- context:
+    cluster: project-cluster
+    user: project-user
+  name: project
+users:
+- name: project-user
+  user:
+    exec:
+      apiVersion: client.authentication.k8s.io/v1beta1
+      command: ./bin/token
+"#,
+    )?;
+
+    // A relative KUBECONFIG is resolved against the working directory
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    let output = cmd
+        .current_dir(env.temp_dir.path())
+        .env("KUBECONFIG", "project/kubeconfig.yaml")
+        .args(["context", "-v", "project"])
+        .output()?;
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(output.status.success());
+
+    let session = fs::read_to_string(stdout.split(':').next().unwrap())?;
+    let expected_ca = format!("certificate-authority: {}", project.join("ca.crt").display());
+    let expected_command = format!("command: {}", project.join("bin/token").display());
+    assert!(session.contains(&expected_ca), "{}", session);
+    assert!(session.contains(&expected_command), "{}", session);
+
+    reset_environment();
+    Ok(())
+}
+
+// =============================================================================
+// Scenario 29: Preferences, extensions and exec settings in session files
+// =============================================================================
+
+#[test]
+#[serial]
+fn session_files_keep_preferences_extensions_and_exec_settings() -> Result<(), Box<dyn std::error::Error>> {
+    reset_environment();
+    let env = setup_multi_kubeconfig_environment();
+    std::env::set_var("KUBECONFIG", env.config_path.display().to_string());
+
+    fs::write(
+        env.kube_dir.join("cloud.yaml"),
+        r#"apiVersion: v1
+kind: Config
+preferences:
+  colors: true
+extensions:
+- name: cloud-provider
+  extension:
+    project: acme
+clusters:
+- cluster:
+    server: https://cloud.example.com:6443
+    extensions:
+    - name: cluster-info
+      extension:
+        region: eu-west-1
+  name: cloud-cluster
+contexts:
+- context:
+    cluster: cloud-cluster
+    user: cloud-user
+    extensions:
+    - name: context-info
+      extension:
+        team: payments
+  name: cloud
+users:
+- name: cloud-user
+  user:
+    exec:
+      apiVersion: client.authentication.k8s.io/v1beta1
+      command: cloud-auth
+      args:
+      - token
+      env:
+      - name: CLOUD_PROFILE
+        value: prod
+      interactiveMode: Never
+      provideClusterInfo: true
+"#,
+    )?;
+
+    let assert_preserved = |session: &str| {
+        for expected in [
+            "colors: true",
+            "name: cloud-provider",
+            "project: acme",
+            "region: eu-west-1",
+            "team: payments",
+            "command: cloud-auth",
+            "- token",
+            "CLOUD_PROFILE",
+            "interactiveMode: Never",
+            "provideClusterInfo: true",
+        ] {
+            assert!(session.contains(expected), "missing {} in {}", expected, session);
+        }
+    };
+
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    let output = cmd.args(["context", "-v", "cloud"]).output()?;
+    assert!(output.status.success());
+    let kubeconfig = String::from_utf8(output.stdout)?.trim().to_string();
+    assert_preserved(&fs::read_to_string(kubeconfig.split(':').next().unwrap())?);
+
+    // Switching namespace rebuilds the session from the session file itself
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    let output = cmd
+        .env("KUBECONFIG", &kubeconfig)
+        .args(["namespace", "-v", "apps", "--force"])
+        .output()?;
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(stdout.contains("cloud_apps"), "{}", stdout);
+    assert_preserved(&fs::read_to_string(stdout.split(':').next().unwrap())?);
+
+    reset_environment();
+    Ok(())
+}
+
+// =============================================================================
+// Scenario 30: Duplicate context names across files
+// =============================================================================
+
+#[test]
+#[serial]
+fn duplicate_contexts_are_told_apart_by_file() -> Result<(), Box<dyn std::error::Error>> {
+    reset_environment();
+    let env = setup_multi_kubeconfig_environment();
+
+    // Same context, cluster and user names as work.yaml, different server
+    let other_path = env.kube_dir.join("other.yaml");
+    fs::write(
+        &other_path,
+        create_kubeconfig_content("work-prod", "work-cluster", "work-user", "other", None)
+            .replace("https://work-cluster.example.com", "https://other.example.com"),
+    )?;
+    std::env::set_var(
+        "KUBECONFIG",
+        format!("{}:{}", env.work_path.display(), other_path.display()),
+    );
+
+    // The bare name picks the first file, like kubectl, and reports the others
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    let output = cmd.args(["context", "-v", "work-prod"]).output()?;
+    let stdout = String::from_utf8(output.stdout)?;
+    let stderr = String::from_utf8(output.stderr)?;
+    assert!(output.status.success(), "{}", stderr);
+    assert!(stdout.contains("work-prod_production"), "{}", stdout);
+    for duplicate in [
+        "context work-prod is also defined in",
+        "cluster work-cluster is also defined in",
+        "user work-user is also defined in",
+    ] {
+        assert!(
+            stderr.contains(&format!("{} {}", duplicate, other_path.display())),
+            "{}",
+            stderr
+        );
+    }
+
+    // File paths aren't matched, only names
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    let output = cmd.args(["context", "-v", "other.yaml"]).output()?;
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr)?.contains("no context exists with the name"));
+
+    let label = format!("work-prod ({})", other_path.display());
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    let output = cmd.args(["context", "-v", &label]).output()?;
+    let stdout = String::from_utf8(output.stdout)?;
+    let stderr = String::from_utf8(output.stderr)?;
+    assert!(output.status.success(), "{}", stderr);
+    assert!(stderr.contains("is also defined in"), "{}", stderr);
+    assert!(stdout.contains("work-prod_other"), "{}", stdout);
+
+    // Cluster and user come from the same file as the context
+    let session = fs::read_to_string(stdout.split(':').next().unwrap())?;
+    assert!(session.contains("https://other.example.com"), "{}", session);
+
+    // Completions list the name once
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    let output = cmd.args(["completion-context", "-v", "work"]).output()?;
+    let stdout = String::from_utf8(output.stdout)?;
+    assert_eq!(stdout.matches("work-prod").count(), 1, "{}", stdout);
+
+    reset_environment();
+    Ok(())
+}
+
+// =============================================================================
+// Scenario 31: Switching by cluster
+// =============================================================================
+
+#[test]
+#[serial]
+fn cluster_switches_to_its_only_context() -> Result<(), Box<dyn std::error::Error>> {
+    reset_environment();
+    let env = setup_multi_kubeconfig_environment();
+    std::env::set_var("KUBECONFIG", env.config_path.display().to_string());
+
+    // A second user for the work cluster
+    fs::write(
+        env.kube_dir.join("readonly.yaml"),
+        create_kubeconfig_content("work-readonly", "work-cluster", "readonly-user", "production", None),
+    )?;
+
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    let output = cmd.args(["cluster", "-v", "personal"]).output()?;
+    let stdout = String::from_utf8(output.stdout)?.trim().to_string();
+    assert!(output.status.success());
+    assert!(stdout.contains("personal-dev_development"), "{}", stdout);
+
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    let output = cmd.env("KUBECONFIG", &stdout).args(["cluster", "-c"]).output()?;
+    assert_eq!(String::from_utf8(output.stdout)?.trim(), "personal-cluster");
+
+    // Two contexts use the work cluster, so kubesess has to ask which one
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    let output = cmd.args(["cluster", "-v", "work-cluster"]).output()?;
+    let stderr = String::from_utf8(output.stderr)?;
+    assert!(!output.status.success());
+    assert!(stderr.contains("select context"), "{}", stderr);
+
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    let output = cmd.args(["cluster", "-v", "missing"]).output()?;
+    assert!(!output.status.success());
+
+    reset_environment();
+    Ok(())
+}
+
+// =============================================================================
+// Scenario 32: Switching user and impersonating
+// =============================================================================
+
+#[test]
+#[serial]
+fn user_switch_keeps_cluster_and_namespace() -> Result<(), Box<dyn std::error::Error>> {
+    reset_environment();
+    let env = setup_multi_kubeconfig_environment();
+    std::env::set_var("KUBECONFIG", env.config_path.display().to_string());
+
+    let session = switch_and_export(&["context", "-v", "work-prod"])?;
+    let original = fs::read_to_string(session.split(':').next().unwrap())?;
+
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    let output = cmd
+        .env("KUBECONFIG", &session)
+        .args(["user", "-v", "personal-user"])
+        .output()?;
+    let user_session = String::from_utf8(output.stdout)?.trim().to_string();
+    assert!(output.status.success());
+    assert!(user_session.contains("work-prod_production@personal-user"), "{}", user_session);
+
+    let content = fs::read_to_string(user_session.split(':').next().unwrap())?;
+    assert!(content.contains("user: personal-user"), "{}", content);
+    assert!(content.contains("cluster: work-cluster"), "{}", content);
+    assert!(content.contains("namespace: production"), "{}", content);
+
+    // Shells on the plain context keep the original user
+    assert_eq!(fs::read_to_string(session.split(':').next().unwrap())?, original);
+
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    let output = cmd
+        .env("KUBECONFIG", &user_session)
+        .args(["user", "--as", "jane", "--as-group", "ops"])
+        .output()?;
+    let impersonating = String::from_utf8(output.stdout)?.trim().to_string();
+    assert!(output.status.success());
+    let content = fs::read_to_string(impersonating.split(':').next().unwrap())?;
+    assert!(content.contains("user: personal-user"), "{}", content);
+    assert!(content.contains("as: jane"), "{}", content);
+    assert!(content.contains("- ops"), "{}", content);
+
+    // Switching namespace keeps the user and the impersonation
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    let output = cmd
+        .env("KUBECONFIG", &impersonating)
+        .args(["namespace", "-v", "apps", "--force"])
+        .output()?;
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(stdout.contains("work-prod_apps@personal-user-as-jane+ops"), "{}", stdout);
+
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    let output = cmd
+        .env("KUBECONFIG", &impersonating)
+        .args(["user", "-c"])
+        .output()?;
+    assert_eq!(String::from_utf8(output.stdout)?.trim(), "personal-user");
+
+    reset_environment();
+    Ok(())
+}
+
+#[test]
+#[serial]
+#[cfg(unix)]
+fn shell_functions_ignore_an_empty_value() -> Result<(), Box<dyn std::error::Error>> {
+    reset_environment();
+    let env = setup_multi_kubeconfig_environment();
+    std::env::set_var(
+        "KUBECONFIG",
+        format!("{}:{}", env.work_path.display(), env.personal_path.display()),
+    );
+    let bin_dir = assert_cmd::cargo::cargo_bin("kubesess")
+        .parent()
+        .unwrap()
+        .to_path_buf();
+    let path = format!("{}:{}", bin_dir.display(), env::var("PATH").unwrap_or_default());
+
+    let output = Command::new("bash")
+        .env("PATH", &path)
+        .args([
+            "-c",
+            r#"eval "$(kubesess init bash)"; kc work-prod && ku "" --as jane && echo "$KUBECONFIG""#,
+        ])
+        .output()?;
+    let stderr = String::from_utf8(output.stderr)?;
+    assert!(output.status.success(), "{}", stderr);
+    assert!(String::from_utf8(output.stdout)?.contains("work-prod_production@work-user-as-jane"));
+
+    reset_environment();
+    Ok(())
+}
+
+// =============================================================================
+// Scenario 33: Context tags
+// =============================================================================
+
+#[test]
+#[serial]
+fn context_tags_filter_options_and_list() -> Result<(), Box<dyn std::error::Error>> {
+    reset_environment();
+    let env = setup_multi_kubeconfig_environment();
+    std::env::set_var("KUBECONFIG", env.config_path.display().to_string());
+    fs::write(
+        env.kube_dir.join("kubesess").join("config.yaml"),
+        r#"tags:
+  work-prod: {env: prod, team: payments}
+  personal-dev: {env: dev}
+"#,
+    )?;
+
+    // "d" matches docker-desktop and personal-dev, the tag leaves one
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    let output = cmd.args(["context", "--tag", "env=dev", "-v", "d"]).output()?;
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(stdout.contains("personal-dev_development"), "{}", stdout);
+
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    let output = cmd.args(["context", "--tag", "env=prod", "-v", "personal-dev"]).output()?;
+    assert!(!output.status.success(), "Untagged contexts should be filtered out");
+
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    let output = cmd.args(["list", "--tag", "team"]).output()?;
+    let stdout = String::from_utf8(output.stdout)?;
+    assert_eq!(stdout.lines().count(), 1, "{}", stdout);
+    assert!(stdout.starts_with("work-prod\twork-cluster\twork-user\tproduction\tenv=prod,team=payments"), "{}", stdout);
+
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    let output = cmd.args(["list", "-o", "json"]).output()?;
+    let entries: serde_yaml::Value = serde_yaml::from_slice(&output.stdout)?;
+    let entries = entries.as_sequence().unwrap();
+    assert_eq!(entries.len(), 3);
+    let work = entries.iter().find(|x| x["name"] == "work-prod").unwrap();
+    assert_eq!(work["tags"]["team"], "payments");
+    assert_eq!(work["file"].as_str(), Some(env.work_path.to_str().unwrap()));
+
+    reset_environment();
+    Ok(())
+}
+
+// =============================================================================
+// Scenario 34: Namespace selector, hidden namespaces and status
+// =============================================================================
+
+#[cfg(unix)]
+#[test]
+#[serial]
+fn namespace_picker_filters_and_shows_status() -> Result<(), Box<dyn std::error::Error>> {
+    reset_environment();
+    let env = setup_multi_kubeconfig_environment();
+    std::env::set_var("KUBECONFIG", env.config_path.display().to_string());
+
+    let all = namespaces_script(&[
+        ("default", "Active", ""),
+        ("kube-system", "Active", ""),
+        ("payments", "Active", r#""team":"payments""#),
+        ("payroll", "Terminating", r#""team":"payments""#),
+    ]);
+    let selected = namespaces_script(&[
+        ("payments", "Active", r#""team":"payments""#),
+        ("payroll", "Terminating", r#""team":"payments""#),
+    ]);
+    let path = fake_kubectl(
+        &env,
+        &format!(
+            "case \"$*\" in\n*--selector=team=payments*)\n{}\n;;\n*--selector=*)\n{}\n;;\n*)\n{}\n;;\nesac",
+            selected,
+            namespaces_script(&[]),
+            all
+        ),
+    );
+
+    // The picker saves what it was offered and selects the last entry
+    let items = env.temp_dir.path().join("items.txt");
+    let picker = format!("tee {} | tail -n 1", items.display());
+
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    let output = cmd
+        .env("PATH", &path)
+        .env("KUBESESS_PICKER", &picker)
+        .arg("namespace")
+        .output()?;
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(stdout.contains("docker-desktop_payroll"), "{}", stdout);
+    let offered = fs::read_to_string(&items)?;
+    assert_eq!(offered.trim_end(), "default\npayments\npayroll  (Terminating)");
+
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    cmd.env("PATH", &path)
+        .env("KUBESESS_PICKER", &picker)
+        .args(["namespace", "--all"])
+        .output()?;
+    assert!(fs::read_to_string(&items)?.contains("kube-system"));
+
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    cmd.env("PATH", &path)
+        .env("KUBESESS_PICKER", &picker)
+        .args(["namespace", "-l", "team=payments"])
+        .output()?;
+    assert_eq!(fs::read_to_string(&items)?.trim_end(), "payments\npayroll  (Terminating)");
+
+    // A selector matching nothing doesn't fall back to unfiltered namespaces
+    fs::remove_file(&items)?;
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    let output = cmd
+        .env("PATH", &path)
+        .env("KUBESESS_PICKER", &picker)
+        .args(["namespace", "-l", "team=nobody"])
+        .output()?;
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr)?;
+    assert!(stderr.contains("no namespace matches the selector team=nobody"), "{}", stderr);
+    assert!(!items.exists(), "The picker shouldn't be shown");
+
+    // Hidden namespaces can still be switched to by name
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    let output = cmd
+        .env("PATH", &path)
+        .args(["namespace", "-v", "kube-system"])
+        .output()?;
+    assert!(String::from_utf8(output.stdout)?.contains("docker-desktop_kube-system"));
+
+    reset_environment();
+    Ok(())
+}
+
+// =============================================================================
+// Scenario 35: Init scripts for every shell
+// =============================================================================
+
+#[test]
+fn init_scripts_define_switch_functions() -> Result<(), Box<dyn std::error::Error>> {
+    for (shell, kc, kn) in [
+        ("nushell", "def --env --wrapped kc ", "def --env --wrapped kn "),
+        ("nu", "def --env --wrapped kc ", "def --env --wrapped kn "),
+        ("elvish", "fn kc {", "fn kn {"),
+        ("xonsh", "aliases[\"kc\"]", "aliases[\"kn\"]"),
+    ] {
+        let mut cmd = Command::cargo_bin("kubesess")?;
+        let output = cmd.args(["init", shell]).output()?;
+        let script = String::from_utf8(output.stdout)?;
+        assert!(output.status.success(), "init {} failed", shell);
+        assert!(script.contains(kc) && script.contains(kn), "{}", script);
+        assert!(script.contains("completion-context"), "{}", script);
+    }
+    Ok(())
+}
+
+// =============================================================================
+// Scenario 36: Native zsh completions with descriptions
+// =============================================================================
+
+#[test]
+fn init_zsh_uses_native_completion() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    let output = cmd.args(["init", "zsh"]).output()?;
+    let zsh = String::from_utf8(output.stdout)?;
+    assert!(zsh.contains("compdef _kubesess_contexts kc kcd"), "{}", zsh);
+    assert!(zsh.contains("--describe"), "{}", zsh);
+    assert!(!zsh.contains("COMPREPLY") && !zsh.contains("complete -F"), "{}", zsh);
+
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    let output = cmd.args(["init", "bash"]).output()?;
+    let bash = String::from_utf8(output.stdout)?;
+    assert!(bash.contains("complete -F _kc_completions kc"), "{}", bash);
+    assert!(!bash.contains("compdef"), "{}", bash);
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn completion_context_describe_prints_cluster_and_namespace() -> Result<(), Box<dyn std::error::Error>> {
+    reset_environment();
+    let env = setup_multi_kubeconfig_environment();
+    std::env::set_var(
+        "KUBECONFIG",
+        format!("{}:{}", env.work_path.display(), env.personal_path.display()),
+    );
+
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    let output = cmd
+        .args(["completion-context", "--describe", "-v", "work"])
+        .output()?;
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout)?,
+        "work-prod\tcluster work-cluster, namespace production\n"
+    );
+
+    reset_environment();
+    Ok(())
+}
+
+#[test]
+#[serial]
+#[cfg(unix)]
+fn completion_namespace_describe_prints_phase() -> Result<(), Box<dyn std::error::Error>> {
+    reset_environment();
+    let env = setup_multi_kubeconfig_environment();
+    std::env::set_var("KUBECONFIG", &env.work_path);
+    let path = fake_kubectl(
+        &env,
+        &namespaces_script(&[("payments", "Active", ""), ("old", "Terminating", "")]),
+    );
+
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    let output = cmd
+        .env("PATH", &path)
+        .args(["completion-namespace", "--describe"])
+        .output()?;
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout)?,
+        "payments\tActive\nold\tTerminating\n"
+    );
+
+    reset_environment();
+    Ok(())
+}
+
+// =============================================================================
+// Scenario 37: Custom function names and partial init scripts
+// =============================================================================
+
+fn init_script(args: &[&str]) -> Result<String, Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    let output = cmd.arg("init").args(args).output()?;
+    assert!(
+        output.status.success(),
+        "init {:?} failed: {}",
+        args,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+#[test]
+fn init_renames_functions() -> Result<(), Box<dyn std::error::Error>> {
+    let script = init_script(&["bash", "--context-cmd", "kctx", "--namespace-cmd", "kns"])?;
+    assert!(script.contains("\nkctx() {") && script.contains("\nkns() {"), "{}", script);
+    assert!(script.contains("complete -F _kc_completions kctx"), "{}", script);
+    assert!(script.contains("\nkcd() {"), "Other functions keep their names");
+    assert!(!script.contains("\nkc() {") && !script.contains("\nkn() {"), "{}", script);
+
+    let script = init_script(&["fish", "--prefix", "my"])?;
+    assert!(script.contains("function mykc ") && script.contains("complete -c myknd "), "{}", script);
+    assert!(!script.contains("function kc "), "{}", script);
+    Ok(())
+}
+
+#[test]
+fn init_prints_only_functions_or_completions() -> Result<(), Box<dyn std::error::Error>> {
+    let functions = init_script(&["bash", "--only", "functions"])?;
+    assert!(functions.contains("\nkc() {"), "{}", functions);
+    assert!(!functions.contains("complete -F"), "{}", functions);
+
+    let completions = init_script(&["bash", "--only", "completions", "--prefix", "x"])?;
+    assert!(completions.contains("complete -F _kc_completions xkc"), "{}", completions);
+    assert!(!completions.contains("__kubesess_switch"), "{}", completions);
+
+    // Nushell functions only refer to the completers when they are printed too
+    let functions = init_script(&["nushell", "--only", "functions"])?;
+    assert!(!functions.contains("nu-complete"), "{}", functions);
+    let both = init_script(&["nushell"])?;
+    assert!(
+        both.find("def \"nu-complete kubesess contexts\"") < both.find("def --env --wrapped kc "),
+        "{}",
+        both
+    );
+    Ok(())
+}
+
+#[test]
+fn init_rejects_invalid_function_names() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    cmd.args(["init", "bash", "--context-cmd", "k c"])
+        .assert()
+        .failure();
+    Ok(())
+}
+
+// =============================================================================
+// Scenario 38: Every shell completes through kubesess
+// =============================================================================
+
+#[test]
+fn init_completions_use_kubesess_describe() -> Result<(), Box<dyn std::error::Error>> {
+    for shell in ["bash", "zsh", "fish", "powershell", "nushell", "elvish", "xonsh"] {
+        let script = init_script(&[shell])?;
+        assert!(!script.contains("kubectl"), "{}: {}", shell, script);
+        assert!(
+            script.contains("completion-context") && script.contains("completion-namespace"),
+            "{}: {}",
+            shell,
+            script
+        );
+        assert!(script.contains("--describe"), "{}: {}", shell, script);
+    }
+    Ok(())
+}
+
+#[test]
+#[serial]
+#[cfg(unix)]
+fn bash_completion_lists_contexts_from_kubesess() -> Result<(), Box<dyn std::error::Error>> {
+    reset_environment();
+    let env = setup_multi_kubeconfig_environment();
+    std::env::set_var(
+        "KUBECONFIG",
+        format!("{}:{}", env.work_path.display(), env.personal_path.display()),
+    );
+    let bin_dir = assert_cmd::cargo::cargo_bin("kubesess")
+        .parent()
+        .unwrap()
+        .to_path_buf();
+    let path = format!("{}:{}", bin_dir.display(), env::var("PATH").unwrap_or_default());
+
+    let output = Command::new("bash")
+        .env("PATH", &path)
+        .args([
+            "-c",
+            r#"eval "$(kubesess init bash)"; COMP_WORDS=(kc dev); _kc_completions; printf '%s\n' "${COMPREPLY[@]}""#,
+        ])
+        .output()?;
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout)?, "personal-dev\n");
+
+    reset_environment();
+    Ok(())
+}
+
+// =============================================================================
+// Scenario 39: Completions for kubesess itself
+// =============================================================================
+
+#[test]
+fn completions_are_generated_for_every_shell() -> Result<(), Box<dyn std::error::Error>> {
+    // Only the code kubesess adds to clap's script contains these
+    let injected: [(&str, &[&str]); 5] = [
+        ("bash", &["_kubesess_values() {", "complete -F _kubesess_values"]),
+        ("zsh", &[":VALUE:_kubesess_values'", "_kubesess_describe() {"]),
+        ("fish", &["-f -a '(kubesess completion-context --describe 2>/dev/null)'"]),
+        (
+            "powershell",
+            &["# kubesess completes the values of -v itself", "'kubesess;context' { 'completion-context' }"],
+        ),
+        (
+            "elvish",
+            &["# kubesess completes the values of -v itself", "&'kubesess;context'=completion-context"],
+        ),
+    ];
+    for (shell, markers) in injected {
+        let mut cmd = Command::cargo_bin("kubesess")?;
+        let output = cmd.args(["completions", shell]).output()?;
+        assert!(output.status.success(), "completions {} failed", shell);
+        let script = String::from_utf8(output.stdout)?;
+        assert!(script.contains("default-namespace"), "{}: {}", shell, script);
+        for marker in markers {
+            assert!(script.contains(marker), "{} lacks {}: {}", shell, marker, script);
+        }
+    }
+    Ok(())
+}
+
+#[test]
+#[serial]
+#[cfg(unix)]
+fn bash_completions_complete_values_dynamically() -> Result<(), Box<dyn std::error::Error>> {
+    reset_environment();
+    let env = setup_multi_kubeconfig_environment();
+    std::env::set_var(
+        "KUBECONFIG",
+        format!("{}:{}", env.work_path.display(), env.personal_path.display()),
+    );
+    let bin_dir = assert_cmd::cargo::cargo_bin("kubesess")
+        .parent()
+        .unwrap()
+        .to_path_buf();
+    let path = format!("{}:{}", bin_dir.display(), env::var("PATH").unwrap_or_default());
+
+    let complete = |line: &str| -> Result<String, Box<dyn std::error::Error>> {
+        let output = Command::new("bash")
+            .env("PATH", &path)
+            .args([
+                "-c",
+                &format!(
+                    r#"eval "$(kubesess completions bash)"; COMP_WORDS=({}); COMP_CWORD=$((${{#COMP_WORDS[@]}} - 1)); _kubesess_values kubesess; printf '%s\n' "${{COMPREPLY[@]}}""#,
+                    line
+                ),
+            ])
+            .output()?;
+        assert!(output.status.success());
+        Ok(String::from_utf8(output.stdout)?)
+    };
+
+    assert_eq!(complete("kubesess context -v dev")?, "personal-dev\n");
+    assert_eq!(complete("kubesess default-context --value work")?, "work-prod\n");
+    assert!(complete("kubesess context --no")?.contains("--no-remember"));
+
+    reset_environment();
+    Ok(())
+}
+
+// =============================================================================
+// Scenario 40: Directories pinned by .kubesess files
+// =============================================================================
+
+fn dir_hook(dir: &std::path::Path) -> Result<(String, String), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    let output = cmd.current_dir(dir).args(["dir", "hook"]).output()?;
+    assert!(output.status.success());
+    Ok((
+        String::from_utf8(output.stdout)?,
+        String::from_utf8(output.stderr)?,
+    ))
+}
+
+#[test]
+#[serial]
+fn dir_pin_switches_in_subdirectories() -> Result<(), Box<dyn std::error::Error>> {
+    reset_environment();
+    let env = setup_multi_kubeconfig_environment();
+    let project = env.temp_dir.path().join("project");
+    let nested = project.join("src");
+    fs::create_dir_all(&nested)?;
+
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    cmd.current_dir(&project)
+        .args(["dir", "pin", "personal-dev", "-n", "payments"])
+        .assert()
+        .success();
+    assert!(project.join(".kubesess").is_file());
+
+    let (stdout, _) = dir_hook(&nested)?;
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 2, "{}", stdout);
+    assert_eq!(lines[0], project.canonicalize()?.join(".kubesess").to_str().unwrap());
+
+    let session = lines[1].split(':').next().unwrap();
+    let session = fs::read_to_string(session)?;
+    assert!(session.contains("current-context: personal-dev"), "{}", session);
+    assert!(session.contains("namespace: payments"), "{}", session);
+
+    // Outside of the pinned directory nothing applies
+    let (stdout, stderr) = dir_hook(env.temp_dir.path())?;
+    assert!(stdout.is_empty() && stderr.is_empty(), "{}{}", stdout, stderr);
+
+    reset_environment();
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn dir_hook_requires_trust() -> Result<(), Box<dyn std::error::Error>> {
+    reset_environment();
+    let env = setup_multi_kubeconfig_environment();
+    let project = env.temp_dir.path().join("project");
+    fs::create_dir_all(&project)?;
+    fs::write(project.join(".kubesess"), "context: work-prod\n")?;
+
+    let (stdout, stderr) = dir_hook(&project)?;
+    assert!(stdout.is_empty(), "{}", stdout);
+    assert!(stderr.contains("is not trusted"), "{}", stderr);
+
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    cmd.current_dir(&project)
+        .args(["dir", "trust"])
+        .assert()
+        .success();
+    let (stdout, _) = dir_hook(&project)?;
+    assert_eq!(stdout.lines().count(), 2, "{}", stdout);
+
+    // Changing the file needs it to be trusted again
+    fs::write(project.join(".kubesess"), "context: personal-dev\n")?;
+    let (stdout, stderr) = dir_hook(&project)?;
+    assert!(stdout.is_empty(), "{}", stdout);
+    assert!(stderr.contains("changed since it was trusted"), "{}", stderr);
+
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    cmd.current_dir(&project)
+        .args(["dir", "trust"])
+        .assert()
+        .success();
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    cmd.current_dir(&project)
+        .args(["dir", "untrust"])
+        .assert()
+        .success();
+    let (stdout, stderr) = dir_hook(&project)?;
+    assert!(stdout.is_empty(), "{}", stdout);
+    assert!(stderr.contains("is not trusted"), "{}", stderr);
+
+    reset_environment();
+    Ok(())
+}
+
+#[test]
+#[serial]
+#[cfg(unix)]
+fn bash_dir_hook_switches_and_restores() -> Result<(), Box<dyn std::error::Error>> {
+    reset_environment();
+    let env = setup_multi_kubeconfig_environment();
+    let project = env.temp_dir.path().join("project");
+    fs::create_dir_all(&project)?;
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    cmd.current_dir(&project)
+        .args(["dir", "pin", "work-prod"])
+        .assert()
+        .success();
+
+    let bin_dir = assert_cmd::cargo::cargo_bin("kubesess")
+        .parent()
+        .unwrap()
+        .to_path_buf();
+    let path = format!("{}:{}", bin_dir.display(), env::var("PATH").unwrap_or_default());
+
+    let output = Command::new("bash")
+        .env("PATH", &path)
+        .env("KUBECONFIG", "/original")
+        .current_dir(env.temp_dir.path())
+        .args([
+            "-c",
+            r#"eval "$(kubesess init bash)"
+__kubesess_dir_hook; echo "$KUBECONFIG"
+cd project; __kubesess_dir_hook; echo "$KUBECONFIG"
+cd ..; __kubesess_dir_hook; echo "$KUBECONFIG""#,
+        ])
+        .output()?;
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout)?;
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines[0], "/original");
+    assert!(lines[1].contains("/kubesess/cache/work-prod_production"), "{}", stdout);
+    assert_eq!(lines[2], "/original");
+
+    reset_environment();
+    Ok(())
+}
+
+#[test]
+fn every_shell_has_a_dir_hook() -> Result<(), Box<dyn std::error::Error>> {
+    for shell in ["bash", "zsh", "fish", "powershell", "nushell", "elvish", "xonsh"] {
+        let hook = init_script(&[shell, "--only", "dir-hook"])?;
+        assert!(
+            hook.contains("kubesess dir hook") || hook.contains(r#"["kubesess", "dir", "hook"]"#),
+            "{}: {}",
+            shell,
+            hook
+        );
+        let script = init_script(&[shell, "--no-dir-hook"])?;
+        assert!(!script.contains("dir hook"), "{}: {}", shell, script);
+    }
+    Ok(())
+}
+
+// =============================================================================
+// Scenario 41: Status of the session and global context
+// =============================================================================
+
+fn status() -> Result<(String, String), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    let output = cmd.arg("status").output()?;
+    assert!(output.status.success());
+    Ok((
+        String::from_utf8(output.stdout)?,
+        String::from_utf8(output.stderr)?,
+    ))
+}
+
+#[test]
+#[serial]
+fn status_shows_session_and_global_context() -> Result<(), Box<dyn std::error::Error>> {
+    reset_environment();
+    let env = setup_multi_kubeconfig_environment();
+    std::env::set_var(
+        "KUBECONFIG",
+        format!("{}:{}", env.config_path.display(), env.work_path.display()),
+    );
+    let session = switch_and_export(&["context", "-v", "work-prod"])?;
+    let session_file = session.split(':').next().unwrap().to_owned();
+
+    let (stdout, stderr) = status()?;
+    assert!(stderr.is_empty(), "{}", stderr);
+    let session_block = stdout.split("Global").next().unwrap();
+    assert!(session_block.contains("context:   work-prod"), "{}", stdout);
+    assert!(session_block.contains("namespace: production"), "{}", stdout);
+    assert!(session_block.contains(&session_file), "{}", stdout);
+    assert!(
+        session_block.contains(&format!("source:    {}", env.work_path.display())),
+        "{}",
+        stdout
+    );
+    let global_block = stdout.split("Global").nth(1).unwrap();
+    assert!(global_block.contains("context:   docker-desktop"), "{}", stdout);
+    assert!(global_block.contains("differs from the session"), "{}", stdout);
+    assert!(
+        stdout.contains(&format!("  {}\n", env.personal_path.display())),
+        "Should list every KUBECONFIG entry: {}",
+        stdout
+    );
+
+    reset_environment();
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn status_warns_about_stale_and_missing_session_files() -> Result<(), Box<dyn std::error::Error>> {
+    reset_environment();
+    let env = setup_multi_kubeconfig_environment();
+    std::env::set_var("KUBECONFIG", &env.work_path);
+    let session = switch_and_export(&["context", "-v", "work-prod"])?;
+    let session_file = session.split(':').next().unwrap().to_owned();
+
+    // The cloud CLI rotated the endpoint
+    let content = fs::read_to_string(&env.work_path)?;
+    fs::write(&env.work_path, content.replace("server: ", "server: https://rotated.example.com#"))?;
+    let (_, stderr) = status()?;
+    assert!(stderr.contains("session file is stale, cluster work-cluster changed"), "{}", stderr);
+
+    fs::remove_file(&session_file)?;
+    let (stdout, stderr) = status()?;
+    assert!(stdout.contains("(missing)"), "{}", stdout);
+    assert!(stderr.contains("is missing"), "{}", stderr);
+
+    reset_environment();
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn status_does_not_flag_a_switched_user_as_stale() -> Result<(), Box<dyn std::error::Error>> {
+    reset_environment();
+    let env = setup_multi_kubeconfig_environment();
+    std::env::set_var(
+        "KUBECONFIG",
+        format!("{}:{}", env.work_path.display(), env.personal_path.display()),
+    );
+    switch_and_export(&["context", "-v", "work-prod"])?;
+    switch_and_export(&["user", "-v", "personal-user", "--as", "jane"])?;
+
+    let (_, stderr) = status()?;
+    assert!(!stderr.contains("stale"), "{}", stderr);
+
+    reset_environment();
+    Ok(())
+}
+
+// =============================================================================
+// Scenario 42: Stale session files are refreshed
+// =============================================================================
+
+fn rotate_server(path: &std::path::Path) -> Result<(), Box<dyn std::error::Error>> {
+    let content = fs::read_to_string(path)?;
+    fs::write(path, content.replace("server: https://", "server: https://rotated."))?;
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn stale_session_is_refreshed_on_use() -> Result<(), Box<dyn std::error::Error>> {
+    reset_environment();
+    let env = setup_multi_kubeconfig_environment();
+    std::env::set_var("KUBECONFIG", &env.work_path);
+    switch_and_export(&["context", "-v", "work-prod"])?;
+    let session = switch_and_export(&["namespace", "-v", "apps", "--force"])?;
+    let session_file = session.split(':').next().unwrap().to_owned();
+
+    // Printing the current namespace or completing leaves the file alone
+    rotate_server(&env.work_path)?;
+    for args in [&["namespace", "-c"][..], &["completion-namespace"][..]] {
+        let mut cmd = Command::cargo_bin("kubesess")?;
+        let output = cmd.args(args).output()?;
+        assert!(!String::from_utf8(output.stderr)?.contains("refreshed"));
+    }
+    assert!(!fs::read_to_string(&session_file)?.contains("rotated"));
+
+    // Switching within the session refreshes it first
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    let output = cmd.args(["namespace", "-v", "batch", "--force"]).output()?;
+    let stderr = String::from_utf8(output.stderr)?;
+    assert!(stderr.contains("refreshed the session file, cluster work-cluster changed"), "{}", stderr);
+    assert!(String::from_utf8(output.stdout)?.contains("work-prod_batch"));
+
+    let content = fs::read_to_string(&session_file)?;
+    assert!(content.contains("https://rotated.work-cluster.example.com:6443"), "{}", content);
+    assert!(content.contains("namespace: apps"), "{}", content);
+
+    // Up to date now
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    let output = cmd.args(["namespace", "-v", "batch", "--force"]).output()?;
+    assert!(!String::from_utf8(output.stderr)?.contains("refreshed"));
+
+    reset_environment();
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn refresh_rebuilds_the_session_or_all_of_them() -> Result<(), Box<dyn std::error::Error>> {
+    reset_environment();
+    let env = setup_multi_kubeconfig_environment();
+    std::env::set_var(
+        "KUBECONFIG",
+        format!("{}:{}", env.work_path.display(), env.personal_path.display()),
+    );
+    let personal = switch_and_export(&["context", "-v", "personal-dev"])?;
+    let personal_file = personal.split(':').next().unwrap().to_owned();
+    std::env::set_var(
+        "KUBECONFIG",
+        format!("{}:{}", env.work_path.display(), env.personal_path.display()),
+    );
+    switch_and_export(&["context", "-v", "work-prod"])?;
+
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    let output = cmd.arg("refresh").output()?;
+    assert!(String::from_utf8(output.stdout)?.contains("Nothing to refresh"));
+
+    rotate_server(&env.work_path)?;
+    rotate_server(&env.personal_path)?;
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    let output = cmd.arg("refresh").output()?;
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(stdout.contains("cluster work-cluster changed"), "{}", stdout);
+    assert!(!stdout.contains("personal-cluster"), "{}", stdout);
+
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    let output = cmd.args(["refresh", "--all"]).output()?;
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(stdout.contains("cluster personal-cluster changed"), "{}", stdout);
+    assert!(!stdout.contains("work-cluster"), "{}", stdout);
+    assert!(fs::read_to_string(&personal_file)?.contains("rotated.personal-cluster"));
+
+    reset_environment();
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn refresh_keeps_the_switched_user() -> Result<(), Box<dyn std::error::Error>> {
+    reset_environment();
+    let env = setup_multi_kubeconfig_environment();
+    std::env::set_var(
+        "KUBECONFIG",
+        format!("{}:{}", env.work_path.display(), env.personal_path.display()),
+    );
+    switch_and_export(&["context", "-v", "work-prod"])?;
+    switch_and_export(&["user", "-v", "personal-user", "--as", "jane"])?;
+
+    rotate_server(&env.work_path)?;
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    let output = cmd.arg("refresh").output()?;
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(stdout.contains("cluster work-cluster changed"), "{}", stdout);
+
+    let session = std::env::var("KUBECONFIG")?;
+    let content = fs::read_to_string(session.split(':').next().unwrap())?;
+    assert!(content.contains("user: personal-user"), "{}", content);
+    assert!(content.contains("as: jane"), "{}", content);
+
+    reset_environment();
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn refresh_reports_sessions_it_cannot_rebuild() -> Result<(), Box<dyn std::error::Error>> {
+    reset_environment();
+    let env = setup_multi_kubeconfig_environment();
+    std::env::set_var("KUBECONFIG", &env.work_path);
+    switch_and_export(&["context", "-v", "work-prod"])?;
+
+    let content = fs::read_to_string(&env.work_path)?;
+    fs::write(&env.work_path, content.replace("name: work-prod", "name: work-staging"))?;
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    let output = cmd.arg("refresh").output()?;
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(stdout.contains("Could not refresh"), "{}", stdout);
+    assert!(stdout.contains("context work-prod no longer exists"), "{}", stdout);
+    assert!(!stdout.contains("Nothing to refresh"), "{}", stdout);
+
+    reset_environment();
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn refresh_on_use_can_be_disabled_in_settings() -> Result<(), Box<dyn std::error::Error>> {
+    reset_environment();
+    let env = setup_multi_kubeconfig_environment();
+    fs::write(
+        env.kube_dir.join("kubesess").join("config.yaml"),
+        "refresh-sessions: false\n",
+    )?;
+    std::env::set_var("KUBECONFIG", &env.work_path);
+    let session = switch_and_export(&["context", "-v", "work-prod"])?;
+
+    rotate_server(&env.work_path)?;
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    let output = cmd.args(["namespace", "-v", "apps", "--force"]).output()?;
+    assert!(!String::from_utf8(output.stderr)?.contains("refreshed"));
+    let content = fs::read_to_string(session.split(':').next().unwrap())?;
+    assert!(!content.contains("rotated"), "{}", content);
+
+    reset_environment();
+    Ok(())
+}
+
+// =============================================================================
+// Scenario 43: Credential health checks against a mock API server
+// =============================================================================
+
+/// Serves every request with `status` and `body` on a local port, returning
+/// the server URL
+fn mock_api_server(status: &str, body: &'static str) -> String {
+    use std::io::{BufRead, BufReader};
+    use std::net::TcpListener;
+
+    let listener = TcpListener::bind("127.0.0.1:0").expect("Failed to bind mock API server");
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let status = status.to_owned();
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let mut reader = BufReader::new(&stream);
+            let mut line = String::new();
+            while reader.read_line(&mut line).map(|n| n > 0).unwrap_or(false) && line != "\r\n" {
+                line.clear();
+            }
+            let _ = write!(
+                &stream,
+                "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+        }
+    });
+    url
+}
+
+const VERSION_BODY: &str = r#"{"major":"1","minor":"31","gitVersion":"v1.31.0","gitCommit":"abc","gitTreeState":"clean","buildDate":"2024-08-13T07:28:49Z","goVersion":"go1.22.5","compiler":"gc","platform":"linux/amd64"}"#;
+const UNAUTHORIZED_BODY: &str = r#"{"kind":"Status","apiVersion":"v1","metadata":{},"status":"Failure","message":"Unauthorized","reason":"Unauthorized","code":401}"#;
+
+/// Adds a `mock` context for `server` authenticating with `user` to KUBECONFIG
+fn mock_context(env: &MultiKubeconfigEnv, server: &str, user: &str) {
+    let path = env.kube_dir.join("mock.yaml");
+    fs::write(
+        &path,
+        format!(
+            r#"apiVersion: v1
+kind: Config
+clusters:
+- cluster:
+    server: {}
+  name: mock-cluster
+contexts:
+- context:
+    cluster: mock-cluster
+    user: mock-user
+  name: mock
+users:
+- name: mock-user
+  user:
+{}
+"#,
+            server, user
+        ),
+    )
+    .expect("Failed to write mock kubeconfig");
+    std::env::set_var(
+        "KUBECONFIG",
+        format!("{}:{}", env.work_path.display(), path.display()),
+    );
+}
+
+const TOKEN_USER: &str = "    token: secret";
+
+fn check(args: &[&str]) -> Result<(bool, String, String), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    let output = cmd.arg("check").args(args).output()?;
+    Ok((
+        output.status.success(),
+        String::from_utf8(output.stdout)?,
+        String::from_utf8(output.stderr)?,
+    ))
+}
+
+#[test]
+#[serial]
+fn check_reports_reachable_with_version_and_timing() -> Result<(), Box<dyn std::error::Error>> {
+    reset_environment();
+    let env = setup_multi_kubeconfig_environment();
+    mock_context(&env, &mock_api_server("200 OK", VERSION_BODY), TOKEN_USER);
+
+    let (success, stdout, stderr) = check(&["mock"])?;
+    assert!(success, "{}", stderr);
+    assert!(stdout.contains("mock is reachable in "), "{}", stdout);
+    assert!(stdout.contains("ms, Kubernetes v1.31.0"), "{}", stdout);
+
+    // Without a context, the one of the session is checked
+    switch_and_export(&["context", "-v", "mock"])?;
+    let (success, stdout, _) = check(&[])?;
+    assert!(success);
+    assert!(stdout.starts_with("mock is reachable"), "{}", stdout);
+
+    reset_environment();
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn check_reports_unauthorized_credentials() -> Result<(), Box<dyn std::error::Error>> {
+    reset_environment();
+    let env = setup_multi_kubeconfig_environment();
+    mock_context(&env, &mock_api_server("401 Unauthorized", UNAUTHORIZED_BODY), TOKEN_USER);
+
+    let (success, stdout, stderr) = check(&["mock"])?;
+    assert!(!success);
+    assert!(stdout.is_empty(), "{}", stdout);
+    assert!(stderr.contains("mock is unauthorized after "), "{}", stderr);
+    assert!(stderr.contains("Unauthorized"), "{}", stderr);
+
+    reset_environment();
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+#[serial]
+fn check_reports_expired_exec_credentials() -> Result<(), Box<dyn std::error::Error>> {
+    use std::os::unix::fs::PermissionsExt;
+
+    reset_environment();
+    let env = setup_multi_kubeconfig_environment();
+    let plugin = env.kube_dir.join("expired-token");
+    fs::write(&plugin, "#!/bin/sh\necho 'the refresh token has expired' >&2\nexit 1\n")?;
+    fs::set_permissions(&plugin, fs::Permissions::from_mode(0o755))?;
+    mock_context(
+        &env,
+        &mock_api_server("200 OK", VERSION_BODY),
+        &format!(
+            "    exec:\n      apiVersion: client.authentication.k8s.io/v1\n      command: {}\n      interactiveMode: Never",
+            plugin.display()
+        ),
+    );
+
+    let (success, _, stderr) = check(&["mock"])?;
+    assert!(!success);
+    assert!(
+        stderr.contains("the credentials of mock are expired or could not be obtained"),
+        "{}",
+        stderr
+    );
+    assert!(stderr.contains("the refresh token has expired"), "{}", stderr);
+
+    reset_environment();
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn check_reports_unreachable_servers() -> Result<(), Box<dyn std::error::Error>> {
+    reset_environment();
+    let env = setup_multi_kubeconfig_environment();
+    // Nothing listens on the port anymore
+    let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+    let server = format!("http://{}", listener.local_addr()?);
+    drop(listener);
+    mock_context(&env, &server, TOKEN_USER);
+
+    let (success, _, stderr) = check(&["mock"])?;
+    assert!(!success);
+    assert!(stderr.contains("mock is unreachable after "), "{}", stderr);
+
+    let (success, _, stderr) = check(&["missing"])?;
+    assert!(!success);
+    assert!(stderr.contains("no context exists with the name missing"), "{}", stderr);
+
+    reset_environment();
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn verify_only_switches_to_accepted_credentials() -> Result<(), Box<dyn std::error::Error>> {
+    reset_environment();
+    let env = setup_multi_kubeconfig_environment();
+    mock_context(&env, &mock_api_server("401 Unauthorized", UNAUTHORIZED_BODY), TOKEN_USER);
+
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    let output = cmd.args(["context", "-v", "mock", "--verify"]).output()?;
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty(), "The shell should keep its KUBECONFIG");
+    let stderr = String::from_utf8(output.stderr)?;
+    assert!(stderr.contains("mock is unauthorized"), "{}", stderr);
+
+    mock_context(&env, &mock_api_server("200 OK", VERSION_BODY), TOKEN_USER);
+    let mut cmd = Command::cargo_bin("kubesess")?;
+    let output = cmd.args(["context", "-v", "mock", "--verify"]).output()?;
+    assert!(output.status.success());
+    assert!(String::from_utf8(output.stdout)?.contains("/kubesess/cache/mock"));
+    let stderr = String::from_utf8(output.stderr)?;
+    assert!(stderr.contains("mock is reachable in "), "{}", stderr);
+
+    reset_environment();
+    Ok(())
+}